@@ -0,0 +1,197 @@
+use std::io::{self, BufRead, Seek, Write};
+use image::{
+	codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+	imageops,
+	AnimationDecoder,
+	DynamicImage,
+	Frame,
+	ImageFormat,
+	ImageReader,
+	ImageResult,
+	RgbaImage
+};
+use crate::{encoding::Format, ConvertError, ConvertOptions, PixelOrder};
+
+/// Frames of an animated image stacked top to bottom, along with how long each is shown
+pub struct Animation {
+	pub image: DynamicImage,
+	/// Milliseconds per frame, empty for still images
+	pub frame_durations: Vec<u32>
+}
+
+impl Animation {
+	/// Decodes every frame of an animated GIF, PNG or WebP image. Other images, PNGs that aren't
+	/// APNGs, still WebPs and single-frame GIFs are decoded as they are without frame durations,
+	/// so animated and still images can be converted together
+	pub fn decode<R: BufRead + Seek>(reader: ImageReader<R>) -> ImageResult<Animation> {
+		let frames = match reader.format() {
+			Some(ImageFormat::Gif) => GifDecoder::new(reader.into_inner())?.into_frames().collect_frames()?,
+			Some(ImageFormat::Png) => {
+				let decoder = PngDecoder::new(reader.into_inner())?;
+				match decoder.is_apng()? {
+					false => return Ok(Animation::still(DynamicImage::from_decoder(decoder)?)),
+					true => decoder.apng()?.into_frames().collect_frames()?
+				}
+			},
+			Some(ImageFormat::WebP) => {
+				let decoder = WebPDecoder::new(reader.into_inner())?;
+				match decoder.has_animation() {
+					false => return Ok(Animation::still(DynamicImage::from_decoder(decoder)?)),
+					true => decoder.into_frames().collect_frames()?
+				}
+			},
+			_ => return Ok(Animation::still(reader.decode()?))
+		};
+
+		match frames.len() {
+			1 => Ok(Animation::still(DynamicImage::ImageRgba8(stack(frames)))),
+			_ => {
+				let frame_durations = frames.iter().map(duration).collect();
+				Ok(Animation {
+					image: DynamicImage::ImageRgba8(stack(frames)),
+					frame_durations
+				})
+			}
+		}
+	}
+
+	fn still(image: DynamicImage) -> Animation {
+		Animation {
+			image,
+			frame_durations: Vec::new()
+		}
+	}
+
+	/// Whether there's more than one frame
+	pub fn is_animated(&self) -> bool {
+		!self.frame_durations.is_empty()
+	}
+}
+
+/// Display time of `frame` in milliseconds, following browsers in showing frames
+/// of 10 ms or less, which many GIF encoders write as "as fast as possible", for 100 ms
+fn duration(frame: &Frame) -> u32 {
+	let (numerator, denominator) = frame.delay().numer_denom_ms();
+	match numerator.div_ceil(denominator.max(1)) {
+		0..=10 => 100,
+		milliseconds => milliseconds
+	}
+}
+
+/// Frames are full canvas size, the decoders already compose them onto the previous ones
+fn stack(frames: Vec<Frame>) -> RgbaImage {
+	let (width, height) = frames.first().map(|frame| frame.buffer().dimensions()).unwrap_or((0, 0));
+	let mut image = RgbaImage::new(width, height * (frames.len() as u32));
+	for (index, frame) in frames.into_iter().enumerate() {
+		imageops::replace(&mut image, frame.buffer(), 0, (index as i64) * (height as i64));
+	}
+	image
+}
+
+/// Checks that an image `dimensions` tall splits into one equally tall frame per duration
+pub(crate) fn validate(frame_durations: &[u32], dimensions: (u32, u32)) -> Result<(), ConvertError> {
+	match frame_durations.is_empty() || dimensions.1.is_multiple_of(frame_durations.len() as u32) {
+		false => Err(ConvertError::FrameCount {
+			height: dimensions.1,
+			frames: frame_durations.len()
+		}),
+		true => Ok(())
+	}
+}
+
+/// `<name>_frame_durations` table in milliseconds
+pub(crate) fn write_frame_table(output: &mut dyn Write, options: &ConvertOptions) -> io::Result<()> {
+	let durations = &options.frame_durations;
+	if durations.is_empty() {
+		return Ok(());
+	}
+	let name = &options.name;
+	let list = durations.iter().map(|duration| duration.to_string()).collect::<Vec<_>>().join(", ");
+
+	writeln!(output, "// Milliseconds each frame is shown for, {} ms in total", total(durations))?;
+	match options.format {
+		Format::Glsl => writeln!(
+			output,
+			"const int {}_frame_durations[{}] = {}{}{};\n",
+			name,
+			durations.len(),
+			options.glsl_version.array_begin(&format!("int[{}]", durations.len())),
+			list,
+			options.glsl_version.array_end()
+		),
+		Format::Hlsl => writeln!(output, "static const int {}_frame_durations[{}] = {{{}}};\n", name, durations.len(), list),
+		// Indexed with the loop counter, which WGSL only allows on variables
+		Format::Wgsl => writeln!(
+			output,
+			"var<private> {0}_frame_durations: array<i32, {1}> = array<i32, {1}>({2});\n",
+			name,
+			durations.len(),
+			list
+		)
+	}
+}
+
+/// Sum of the durations, kept above 0 since the animation time is taken modulo it
+fn total(durations: &[u32]) -> u64 {
+	durations.iter().map(|duration| *duration as u64).sum::<u64>().max(1)
+}
+
+/// `<name>_frame`, picking the frame shown at a time in seconds such as `frameTimeCounter`,
+/// and `<name>_frame_texel`, reading through `texel` at `coord` within one frame
+pub(crate) fn write_frame_helpers(output: &mut dyn Write, options: &ConvertOptions, order: PixelOrder, texel: &str) -> io::Result<()> {
+	let durations = &options.frame_durations;
+	if durations.is_empty() {
+		return Ok(());
+	}
+	let name = &options.name;
+	let frames = durations.len();
+	let frame_height = order.dimensions.1 / (frames as u32);
+	let total = total(durations);
+	// The first frame ends up at the bottom when the rows are flipped
+	let offset = match order.flip_y {
+		false => format!("frame * {}", frame_height),
+		true => format!("({} - frame) * {}", frames - 1, frame_height)
+	};
+
+	match options.format {
+		Format::Glsl => {
+			writeln!(output, "\n// Frame shown `time` seconds into the looping animation, e.g. with frameTimeCounter")?;
+			writeln!(output, "int {}_frame(float time) {{", name)?;
+			writeln!(output, "\tint elapsed = int(mod(time * 1000.0, {}.0));", total)?;
+			writeln!(output, "\tfor (int frame = 0; frame < {}; frame++) {{", frames)?;
+			writeln!(output, "\t\telapsed -= {}_frame_durations[frame];", name)?;
+			writeln!(output, "\t\tif (elapsed < 0) {{\n\t\t\treturn frame;\n\t\t}}")?;
+			writeln!(output, "\t}}\n\treturn {};\n}}", frames - 1)?;
+			writeln!(output, "\nvec4 {}_frame_texel(ivec2 coord, int frame) {{", name)?;
+			writeln!(output, "\tcoord.y += {};", offset)?;
+			writeln!(output, "\treturn {};\n}}", texel)
+		},
+		Format::Hlsl => {
+			writeln!(output, "\n// Frame shown `time` seconds into the looping animation")?;
+			writeln!(output, "int {}_frame(float time) {{", name)?;
+			// `fmod` truncates towards zero, so looping goes through floor division
+			writeln!(output, "\tfloat milliseconds = time * 1000.0;")?;
+			writeln!(output, "\tint elapsed = (int)(milliseconds - {0}.0 * floor(milliseconds / {0}.0));", total)?;
+			writeln!(output, "\tfor (int frame = 0; frame < {}; frame++) {{", frames)?;
+			writeln!(output, "\t\telapsed -= {}_frame_durations[frame];", name)?;
+			writeln!(output, "\t\tif (elapsed < 0) {{\n\t\t\treturn frame;\n\t\t}}")?;
+			writeln!(output, "\t}}\n\treturn {};\n}}", frames - 1)?;
+			writeln!(output, "\nfloat4 {}_frame_texel(int2 coord, int frame) {{", name)?;
+			writeln!(output, "\tcoord.y += {};", offset)?;
+			writeln!(output, "\treturn {};\n}}", texel)
+		},
+		Format::Wgsl => {
+			writeln!(output, "\n// Frame shown `time` seconds into the looping animation")?;
+			writeln!(output, "fn {}_frame(time: f32) -> i32 {{", name)?;
+			writeln!(output, "\tlet milliseconds = time * 1000.0;")?;
+			writeln!(output, "\tvar elapsed = i32(milliseconds - {0}.0 * floor(milliseconds / {0}.0));", total)?;
+			writeln!(output, "\tfor (var frame = 0; frame < {}; frame++) {{", frames)?;
+			writeln!(output, "\t\telapsed -= {}_frame_durations[frame];", name)?;
+			writeln!(output, "\t\tif (elapsed < 0) {{\n\t\t\treturn frame;\n\t\t}}")?;
+			writeln!(output, "\t}}\n\treturn {};\n}}", frames - 1)?;
+			writeln!(output, "\nfn {}_frame_texel(coord: vec2<i32>, frame: i32) -> vec4<f32> {{", name)?;
+			writeln!(output, "\tlet wrapped = coord + vec2<i32>(0, {});", offset)?;
+			writeln!(output, "\treturn {};\n}}", texel)
+		}
+	}
+}
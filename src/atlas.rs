@@ -0,0 +1,176 @@
+use std::{cmp::Reverse, io::{self, Write}};
+use image::{imageops, ColorType, DynamicImage, ImageBuffer, Pixel};
+use crate::{encoding::Format, ConvertError, ConvertOptions, PixelOrder};
+
+/// Named sub-image of an atlas, in source image pixels
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sprite {
+	pub name: String,
+	pub x: u32,
+	pub y: u32,
+	pub width: u32,
+	pub height: u32
+}
+
+/// Several images packed into one, along with where each of them ended up
+pub struct Atlas {
+	pub image: DynamicImage,
+	pub sprites: Vec<Sprite>
+}
+
+impl Atlas {
+	/// Packs `images` onto shelves, tallest first, in an atlas about as wide as it is tall.
+	/// The atlas keeps 16-bit or floating point precision if any of the images has it.
+	pub fn pack(images: &[(String, DynamicImage)]) -> Atlas {
+		let total_area: u64 = images.iter().map(|(_, image)| (image.width() as u64) * (image.height() as u64)).sum();
+		let widest = images.iter().map(|(_, image)| image.width()).max().unwrap_or(0);
+		let width = widest.max((total_area as f64).sqrt().ceil() as u32);
+
+		let mut order: Vec<usize> = (0..images.len()).collect();
+		order.sort_by_key(|index| (Reverse(images[*index].1.height()), Reverse(images[*index].1.width())));
+
+		let mut sprites: Vec<Option<Sprite>> = vec![None; images.len()];
+		let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+		for index in order {
+			let (name, image) = &images[index];
+			if x + image.width() > width {
+				(x, y, shelf_height) = (0, y + shelf_height, 0);
+			}
+			sprites[index] = Some(Sprite {
+				name: name.clone(),
+				x,
+				y,
+				width: image.width(),
+				height: image.height()
+			});
+			x += image.width();
+			shelf_height = shelf_height.max(image.height());
+		}
+		let sprites: Vec<Sprite> = sprites.into_iter().flatten().collect();
+		let size = (
+			sprites.iter().map(|sprite| sprite.x + sprite.width).max().unwrap_or(0),
+			y + shelf_height
+		);
+
+		let colors = images.iter().map(|(_, image)| image.color());
+		let image = match (colors.clone().any(is_float), colors.clone().any(is_16_bit)) {
+			(true, _) => DynamicImage::ImageRgba32F(compose(size, &sprites, images.iter().map(|(_, image)| image.to_rgba32f()))),
+			(false, true) => DynamicImage::ImageRgba16(compose(size, &sprites, images.iter().map(|(_, image)| image.to_rgba16()))),
+			(false, false) => DynamicImage::ImageRgba8(compose(size, &sprites, images.iter().map(|(_, image)| image.to_rgba8())))
+		};
+
+		Atlas { image, sprites }
+	}
+}
+
+fn is_float(color: ColorType) -> bool {
+	matches!(color, ColorType::Rgb32F | ColorType::Rgba32F)
+}
+
+fn is_16_bit(color: ColorType) -> bool {
+	matches!(color, ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16)
+}
+
+/// Copies `images` to the positions of `sprites`, leaving the gaps transparent
+fn compose<P: Pixel>(
+	size: (u32, u32),
+	sprites: &[Sprite],
+	images: impl Iterator<Item = ImageBuffer<P, Vec<P::Subpixel>>>
+) -> ImageBuffer<P, Vec<P::Subpixel>> {
+	let mut atlas = ImageBuffer::new(size.0, size.1);
+	for (sprite, image) in sprites.iter().zip(images) {
+		imageops::replace(&mut atlas, &image, sprite.x as i64, sprite.y as i64);
+	}
+	atlas
+}
+
+/// Checks that sprite names are unique identifiers whose macros aren't reserved and leave the size macros and
+/// `format`'s include guard alone, and that their rectangles lie within `dimensions`
+pub(crate) fn validate(sprites: &[Sprite], format: Format, dimensions: (u32, u32)) -> Result<(), ConvertError> {
+	for (index, sprite) in sprites.iter().enumerate() {
+		let problem = if sprite.name.is_empty() || !sprite.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+			Some("its name can only contain letters, digits and underscores")
+		} else if sprite.name.starts_with('_') || sprite.name.ends_with('_') || sprite.name.contains("__") {
+			// `<NAME>_<SPRITE>` would hold `__`, which the preprocessor reserves
+			Some("its macro would contain the reserved \"__\"")
+		} else if sprites[..index].iter().any(|other| other.name.eq_ignore_ascii_case(&sprite.name)) {
+			Some("another sprite has the same name")
+		} else if sprite.name.eq_ignore_ascii_case("width") || sprite.name.eq_ignore_ascii_case("height") {
+			// `<NAME>_WIDTH` and `<NAME>_HEIGHT` are taken by the size macros
+			Some("its macro would clash with the size macros")
+		} else if sprite.name.eq_ignore_ascii_case(format.name()) {
+			// `<NAME>_GLSL` and `<NAME>_HLSL` are the include guards
+			Some("its macro would clash with the include guard")
+		} else if (sprite.x as u64) + (sprite.width as u64) > dimensions.0 as u64
+			|| (sprite.y as u64) + (sprite.height as u64) > dimensions.1 as u64
+		{
+			Some("it lies outside of the image")
+		} else {
+			None
+		};
+		if let Some(problem) = problem {
+			return Err(ConvertError::InvalidSprite {
+				name: sprite.name.clone(),
+				problem
+			});
+		}
+	}
+	Ok(())
+}
+
+/// Index constants per sprite and the `<name>_rects` table of x, y, width and height,
+/// in array coordinates so flipped outputs address the same sprites
+pub(crate) fn write_sprites(output: &mut dyn Write, options: &ConvertOptions, order: PixelOrder) -> io::Result<()> {
+	let sprites = &options.sprites;
+	if sprites.is_empty() {
+		return Ok(());
+	}
+	let name = &options.name;
+	let macro_prefix = name.to_uppercase();
+
+	for (index, sprite) in sprites.iter().enumerate() {
+		match options.format {
+			Format::Glsl | Format::Hlsl => writeln!(output, "#define {}_{} {}", macro_prefix, sprite.name.to_uppercase(), index)?,
+			Format::Wgsl => writeln!(output, "const {}_{}: i32 = {};", macro_prefix, sprite.name.to_uppercase(), index)?
+		}
+	}
+	output.write_all(b"\n")?;
+
+	let (constructor, end) = match options.format {
+		Format::Glsl => {
+			let version = options.glsl_version;
+			writeln!(
+				output,
+				"const ivec4 {}_rects[{}] = {}",
+				name,
+				sprites.len(),
+				version.array_begin(&format!("ivec4[{}]", sprites.len()))
+			)?;
+			("ivec4", version.array_end())
+		},
+		Format::Hlsl => {
+			writeln!(output, "static const int4 {}_rects[{}] = {{", name, sprites.len())?;
+			("int4", "}")
+		},
+		Format::Wgsl => {
+			writeln!(output, "const {}_rects = array<vec4<i32>, {}>(", name, sprites.len())?;
+			("vec4<i32>", ")")
+		}
+	};
+	for (index, sprite) in sprites.iter().enumerate() {
+		let x = match order.flip_x {
+			false => sprite.x,
+			true => order.dimensions.0 - sprite.x - sprite.width
+		};
+		let y = match order.flip_y {
+			false => sprite.y,
+			true => order.dimensions.1 - sprite.y - sprite.height
+		};
+		write!(output, "\t{}({}, {}, {}, {})", constructor, x, y, sprite.width, sprite.height)?;
+		output.write_all(match (index + 1) == sprites.len() {
+			false => b",\n",
+			true => b"\n"
+		})?;
+	}
+	writeln!(output, "{};\n", end)
+}
@@ -0,0 +1,79 @@
+/// Color space the output values are written in
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorSpace {
+	/// Write the sRGB encoded values as they are stored in the image
+	Srgb,
+	/// Decode sRGB to linear values, for shaders that light in linear space
+	Linear
+}
+
+/// Per-pixel adjustments applied while reading the source image
+#[derive(Clone, Copy, Debug)]
+pub struct ColorConversion {
+	pub color_space: ColorSpace,
+	pub premultiply_alpha: bool
+}
+
+impl ColorConversion {
+	pub fn is_identity(&self) -> bool {
+		self.color_space == ColorSpace::Srgb && !self.premultiply_alpha
+	}
+
+	/// What the channels hold, such as `premultiplied linear RGBA`
+	pub fn describe(&self) -> &'static str {
+		match (self.color_space, self.premultiply_alpha) {
+			(ColorSpace::Srgb, false) => "RGBA",
+			(ColorSpace::Srgb, true) => "premultiplied RGBA",
+			(ColorSpace::Linear, false) => "linear RGBA",
+			(ColorSpace::Linear, true) => "premultiplied linear RGBA"
+		}
+	}
+
+	/// Converts an RGBA color, `is_linear` skipping the sRGB decode for sources that are already linear
+	pub fn apply(&self, color: [f64; 4], is_linear: bool) -> [f64; 4] {
+		let [mut red, mut green, mut blue, alpha] = color;
+		if self.color_space == ColorSpace::Linear && !is_linear {
+			red = srgb_to_linear(red);
+			green = srgb_to_linear(green);
+			blue = srgb_to_linear(blue);
+		}
+		if self.premultiply_alpha {
+			red *= alpha;
+			green *= alpha;
+			blue *= alpha;
+		}
+		[red, green, blue, alpha]
+	}
+
+	/// Undoes `apply` on values read back from a generated array
+	pub fn revert(&self, color: [f64; 4]) -> [f64; 4] {
+		let [mut red, mut green, mut blue, alpha] = color;
+		if self.premultiply_alpha && alpha > 0.0 {
+			red /= alpha;
+			green /= alpha;
+			blue /= alpha;
+		}
+		if self.color_space == ColorSpace::Linear {
+			red = linear_to_srgb(red);
+			green = linear_to_srgb(green);
+			blue = linear_to_srgb(blue);
+		}
+		[red, green, blue, alpha]
+	}
+}
+
+/// sRGB electro-optical transfer function, IEC 61966-2-1
+pub fn srgb_to_linear(value: f64) -> f64 {
+	match value <= 0.04045 {
+		true => value / 12.92,
+		false => ((value + 0.055) / 1.055).powf(2.4)
+	}
+}
+
+/// Inverse of `srgb_to_linear`
+pub fn linear_to_srgb(value: f64) -> f64 {
+	match value <= 0.0031308 {
+		true => value * 12.92,
+		false => 1.055 * value.powf(1.0 / 2.4) - 0.055
+	}
+}
@@ -0,0 +1,453 @@
+use image::{DynamicImage, ImageBuffer, Rgba, Rgba32FImage, RgbaImage};
+use thiserror::Error;
+use crate::{palette, pixels, ColorConversion, Layout, PixelOrder};
+
+#[derive(Error, Debug)]
+pub enum DecodeError {
+	#[error("No generated arrays found, there's neither a `// W x H texels` comment nor a `vec4 image[W][H]` declaration")]
+	NoArrays,
+	#[error("Can't tell how {0} is laid out from its access {1}")]
+	UnknownAccess(String, String),
+	#[error("The declaration of {0} is missing")]
+	MissingDeclaration(String),
+	#[error("{name} holds {found} values where {expected} were expected")]
+	ValueCount {
+		name: String,
+		expected: usize,
+		found: usize
+	},
+	#[error("{0} isn't a number")]
+	InvalidNumber(String),
+	#[error("The {0} table doesn't describe whole rows")]
+	InvalidTable(String)
+}
+
+/// How the pixels of an array are stored, going by the declarations next to it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Storage {
+	Vec4,
+	PackedU32,
+	Palette,
+	Rle
+}
+
+/// Reads every array in source written by this crate back into an image, undoing `conversion`.
+///
+/// Arrays are found through the `// W x H texels, read as ...` comment above them, which tells
+/// the size, layout and encoding. Files without one, written before it was added, are read as
+/// a single column-major `vec4 image[W][H]`.
+pub fn decode(source: &str, conversion: ColorConversion) -> Result<Vec<(String, DynamicImage)>, DecodeError> {
+	let mut headers = Vec::new();
+	let mut offset = 0;
+	for line in source.split_inclusive('\n') {
+		if let Some((dimensions, access)) = parse_header(line) {
+			headers.push((offset, dimensions, access));
+		}
+		offset += line.len();
+	}
+	if headers.is_empty() {
+		return legacy(source, conversion).map(|image| vec![image]);
+	}
+
+	let mut images = Vec::with_capacity(headers.len());
+	for (index, (start, dimensions, access)) in headers.iter().enumerate() {
+		// Everything up to the next array belongs to this one
+		let end = headers.get(index + 1).map(|header| header.0).unwrap_or(source.len());
+		let section = &source[*start..end];
+		let orientation = section.lines().nth(1).unwrap_or_default();
+
+		let (name, storage, layout) = parse_access(section, *dimensions, access)?;
+		let order = PixelOrder {
+			layout,
+			flip_x: orientation.contains("the right column"),
+			flip_y: orientation.contains("the bottom row"),
+			dimensions: *dimensions
+		};
+		let image = decode_array(section, name, storage, order, conversion)?;
+		images.push((String::from(name), image));
+	}
+	Ok(images)
+}
+
+/// Dimensions and element access from a `// W x H texels, read as <access>` line
+fn parse_header(line: &str) -> Option<((u32, u32), &str)> {
+	let (size, access) = line.trim_end().strip_prefix("// ")?.split_once(" texels, read as ")?;
+	let (width, height) = size.split_once(" x ")?;
+	Some(((width.parse().ok()?, height.parse().ok()?), access))
+}
+
+/// Array name, storage and layout from an access such as `sky[x][y]` or `skyFetch(ivec2(x, y))`
+fn parse_access<'s>(section: &str, dimensions: (u32, u32), access: &'s str) -> Result<(&'s str, Storage, Layout), DecodeError> {
+	let name_end = access
+		.find(|character: char| !(character.is_ascii_alphanumeric() || character == '_'))
+		.unwrap_or(access.len())
+	;
+	let (name, index) = access.split_at(name_end);
+	let unknown = || DecodeError::UnknownAccess(String::from(name), String::from(access));
+
+	match index {
+		"[x][y]" => Ok((name, Storage::Vec4, Layout::ColumnMajor)),
+		"[y][x]" => Ok((name, Storage::Vec4, Layout::RowMajor)),
+		"(ivec2(x, y))" => {
+			let name = name.strip_suffix("Fetch").ok_or_else(unknown)?;
+			let storage = if initializer(section, &format!("{}_palette", name)).is_some() {
+				Storage::Palette
+			} else if initializer(section, &format!("{}_run_starts", name)).is_some() {
+				Storage::Rle
+			} else {
+				Storage::PackedU32
+			};
+			// The outer index comes first in the fetch function, e.g. `coord.x * 23 + coord.y`
+			let signature = format!("{}Fetch(ivec2 coord) {{", name);
+			let body = &section[section.find(&signature).ok_or_else(unknown)?..];
+			let body = &body[..body.find("\n}").unwrap_or(body.len())];
+			match (body.find("coord.x"), body.find("coord.y")) {
+				(Some(x), Some(y)) if x < y => Ok((name, storage, Layout::ColumnMajor)),
+				(Some(_), Some(_)) => Ok((name, storage, Layout::RowMajor)),
+				_ => Err(unknown())
+			}
+		},
+		_ if index == format!("[x * {} + y]", dimensions.1) => Ok((name, Storage::Vec4, Layout::ColumnMajor)),
+		_ if index == format!("[y * {} + x]", dimensions.0) => Ok((name, Storage::Vec4, Layout::RowMajor)),
+		_ => Err(unknown())
+	}
+}
+
+/// The `vec4 image[W][H]` written before arrays got a comment describing them
+fn legacy(source: &str, conversion: ColorConversion) -> Result<(String, DynamicImage), DecodeError> {
+	for line in source.lines() {
+		let declaration = line.trim_start();
+		let declaration = declaration.strip_prefix("const ").unwrap_or(declaration);
+		let Some(declaration) = declaration.strip_prefix("vec4 ") else {
+			continue;
+		};
+		let Some((name, sizes)) = declaration.split_once('[') else {
+			continue;
+		};
+		let Some((width, rest)) = sizes.split_once("][") else {
+			continue;
+		};
+		let Some((height, _)) = rest.split_once(']') else {
+			continue;
+		};
+		let (Ok(width), Ok(height)) = (width.parse(), height.parse()) else {
+			continue;
+		};
+
+		let order = PixelOrder {
+			layout: Layout::ColumnMajor,
+			flip_x: false,
+			flip_y: false,
+			dimensions: (width, height)
+		};
+		let image = decode_array(source, name, Storage::Vec4, order, conversion)?;
+		return Ok((String::from(name), image));
+	}
+	Err(DecodeError::NoArrays)
+}
+
+fn decode_array(
+	section: &str,
+	name: &str,
+	storage: Storage,
+	order: PixelOrder,
+	conversion: ColorConversion
+) -> Result<DynamicImage, DecodeError> {
+	let total_pixels = (order.outer_len() as usize) * (order.inner_len() as usize);
+	let array = |name: &str| initializer(section, name).ok_or_else(|| DecodeError::MissingDeclaration(String::from(name)));
+
+	// Colors in the order they're stored in
+	let stored: Vec<[f64; 4]> = match storage {
+		Storage::Vec4 => colors(name, array(name)?, Some(total_pixels))?,
+		Storage::PackedU32 => integers(name, array(name)?, Some(total_pixels))?
+			.into_iter()
+			.map(|word| {
+				let [alpha, red, green, blue] = word.to_be_bytes();
+				[red, green, blue, alpha].map(|value| (value as f64) / 255.0)
+			})
+			.collect(),
+		Storage::Palette => {
+			let palette_name = format!("{}_palette", name);
+			let palette = colors(&palette_name, array(&palette_name)?, None)?;
+			let bits = palette::bits_per_index(palette.len());
+			let per_word = (32 / bits) as usize;
+			let words = integers(name, array(name)?, Some(total_pixels.div_ceil(per_word)))?;
+			(0..total_pixels)
+				.map(|index| {
+					let entry = (words[index / per_word] >> (((index % per_word) as u32) * bits)) & ((1 << bits) - 1);
+					palette.get(entry as usize).copied().ok_or_else(|| DecodeError::InvalidTable(String::from(name)))
+				})
+				.collect::<Result<_, _>>()?
+		},
+		Storage::Rle => {
+			let (starts_name, rows_name) = (format!("{}_run_starts", name), format!("{}_rows", name));
+			let runs = colors(name, array(name)?, None)?;
+			let starts = integers(&starts_name, array(&starts_name)?, Some(runs.len()))?;
+			let rows = integers(&rows_name, array(&rows_name)?, Some((order.outer_len() as usize) + 1))?;
+			expand_runs(&runs, &starts, &rows, order.inner_len()).ok_or(DecodeError::InvalidTable(rows_name))?
+		}
+	};
+
+	let (width, height) = order.dimensions;
+	let mut texels = vec![[0.0; 4]; total_pixels];
+	for (index, color) in stored.into_iter().enumerate() {
+		let (outer, inner) = ((index as u32) / order.inner_len(), (index as u32) % order.inner_len());
+		let (x, y) = order.source(outer, inner);
+		texels[(y as usize) * (width as usize) + (x as usize)] = conversion.revert(color);
+	}
+	Ok(to_image(&texels, (width, height)))
+}
+
+/// Stored colors of run-length encoded rows, `rows` holding the first run of every row and
+/// one past the last run
+fn expand_runs(runs: &[[f64; 4]], starts: &[u32], rows: &[u32], inner_len: u32) -> Option<Vec<[f64; 4]>> {
+	let mut colors = Vec::new();
+	for row in rows.windows(2) {
+		let (first, end) = (row[0] as usize, row[1] as usize);
+		if first >= end || end > runs.len() || starts[first] != 0 {
+			return None;
+		}
+		for run in first..end {
+			let run_end = match run + 1 == end {
+				false => starts[run + 1],
+				true => inner_len
+			};
+			if run_end <= starts[run] || run_end > inner_len {
+				return None;
+			}
+			colors.extend(std::iter::repeat_n(runs[run], (run_end - starts[run]) as usize));
+		}
+	}
+	Some(colors)
+}
+
+/// 8-bit RGBA if every value is a multiple of 1/255, 16-bit RGBA if they're all within 0..1
+/// and floating point otherwise
+fn to_image(texels: &[[f64; 4]], (width, height): (u32, u32)) -> DynamicImage {
+	let values = || texels.iter().flatten();
+	let pixel = |x: u32, y: u32| texels[(y as usize) * (width as usize) + (x as usize)];
+
+	let normalized = values().all(|value| (0.0..=1.0).contains(value));
+	// Values have 7 decimals, far closer to a multiple of 1/255 than a thousandth of a step
+	let eight_bit = values().all(|value| (value * 255.0 - (value * 255.0).round()).abs() < 0.001);
+	match (normalized, eight_bit) {
+		(true, true) => DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| Rgba(pixels::quantize(pixel(x, y))))),
+		(true, false) => DynamicImage::ImageRgba16(ImageBuffer::from_fn(width, height, |x, y| {
+			Rgba(pixel(x, y).map(|value| (value * 65535.0).round() as u16))
+		})),
+		(false, _) => DynamicImage::ImageRgba32F(Rgba32FImage::from_fn(width, height, |x, y| Rgba(pixel(x, y).map(|value| value as f32))))
+	}
+}
+
+/// Text between `=` and `;` of the declaration of `name`
+fn initializer<'s>(section: &'s str, name: &str) -> Option<&'s str> {
+	let mut offset = 0;
+	for line in section.split_inclusive('\n') {
+		let start = offset;
+		offset += line.len();
+
+		let declaration = line.trim_start();
+		if !["const ", "static const ", "var<private> ", "vec4 "].iter().any(|keyword| declaration.starts_with(keyword)) {
+			continue;
+		}
+		let declares = declaration.match_indices(name).any(|(position, _)| {
+			declaration[..position].ends_with(' ') && declaration[(position + name.len())..].starts_with(['[', ' ', ':'])
+		});
+		if declares {
+			let rest = &section[start..];
+			let equals = rest.find('=')?;
+			let end = rest.find(';')?;
+			return rest.get((equals + 1)..end);
+		}
+	}
+	None
+}
+
+/// Numeric literals of an initializer, skipping type names such as `vec4` and the sizes in
+/// `vec4[851]` or `array<vec4<f32>, 37>`
+fn literals(initializer: &str) -> Vec<&str> {
+	let bytes = initializer.as_bytes();
+	let mut literals = Vec::new();
+	let (mut index, mut depth) = (0, 0i32);
+	while index < bytes.len() {
+		let start = index;
+		match bytes[index] {
+			b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
+				while index < bytes.len() && (bytes[index].is_ascii_alphanumeric() || bytes[index] == b'_') {
+					index += 1;
+				}
+			},
+			b'0'..=b'9' | b'-' | b'.' => {
+				index += 1;
+				while index < bytes.len()
+					&& (bytes[index].is_ascii_alphanumeric()
+						|| bytes[index] == b'.'
+						|| (matches!(bytes[index], b'+' | b'-') && matches!(bytes[index - 1], b'e' | b'E') && !initializer[start..].starts_with("0x")))
+				{
+					index += 1;
+				}
+				if depth == 0 {
+					literals.push(&initializer[start..index]);
+				}
+			},
+			b'[' | b'<' => {
+				depth += 1;
+				index += 1;
+			},
+			b']' | b'>' => {
+				depth -= 1;
+				index += 1;
+			},
+			_ => index += 1
+		}
+	}
+	literals
+}
+
+fn check_count(name: &str, found: usize, expected: Option<usize>) -> Result<(), DecodeError> {
+	match expected {
+		Some(expected) if expected != found => Err(DecodeError::ValueCount {
+			name: String::from(name),
+			expected,
+			found
+		}),
+		_ => Ok(())
+	}
+}
+
+/// `vec4`, `float4` or `vec4<f32>` values of an initializer
+fn colors(name: &str, initializer: &str, expected: Option<usize>) -> Result<Vec<[f64; 4]>, DecodeError> {
+	let values = literals(initializer)
+		.into_iter()
+		.map(|literal| literal.parse::<f64>().map_err(|_| DecodeError::InvalidNumber(String::from(literal))))
+		.collect::<Result<Vec<f64>, _>>()?
+	;
+	if !values.len().is_multiple_of(4) {
+		return Err(DecodeError::ValueCount {
+			name: String::from(name),
+			expected: values.len().next_multiple_of(4),
+			found: values.len()
+		});
+	}
+	check_count(name, values.len() / 4, expected)?;
+	Ok(values.chunks_exact(4).map(|color| [color[0], color[1], color[2], color[3]]).collect())
+}
+
+/// Hexadecimal `0x...u` or decimal values of an initializer
+fn integers(name: &str, initializer: &str, expected: Option<usize>) -> Result<Vec<u32>, DecodeError> {
+	let values = literals(initializer)
+		.into_iter()
+		.map(|literal| {
+			let digits = literal.strip_suffix('u').unwrap_or(literal);
+			match digits.strip_prefix("0x") {
+				Some(hex) => u32::from_str_radix(hex, 16),
+				None => digits.parse()
+			}
+			.map_err(|_| DecodeError::InvalidNumber(String::from(literal)))
+		})
+		.collect::<Result<Vec<u32>, _>>()?
+	;
+	check_count(name, values.len(), expected)?;
+	Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+	use image::{DynamicImage, RgbaImage};
+	use crate::{convert, ColorConversion, ColorSpace, ConvertOptions, Encoding, Format, Layout};
+	use super::decode;
+
+	const IDENTITY: ColorConversion = ColorConversion {
+		color_space: ColorSpace::Srgb,
+		premultiply_alpha: false
+	};
+
+	/// Small image with a few repeated colors, so the palette holds them all and rows have runs
+	fn image() -> DynamicImage {
+		DynamicImage::ImageRgba8(RgbaImage::from_fn(5, 3, |x, y| match (x / 2 + y) % 3 {
+			0 => image::Rgba([255, 0, 0, 255]),
+			1 => image::Rgba([12, 200, 34, 128]),
+			_ => image::Rgba([x as u8 * 40, 7, y as u8 * 90, 0])
+		}))
+	}
+
+	fn round_trip(options: &ConvertOptions) {
+		let image = image();
+		let source = convert(&image, options).unwrap().source;
+		let decoded = decode(&source, IDENTITY).unwrap();
+		assert_eq!(decoded.len(), 1, "{:?}", options);
+		assert_eq!(decoded[0].0, "image", "{:?}", options);
+		assert_eq!(decoded[0].1.to_rgba8(), image.to_rgba8(), "{:?}", options);
+	}
+
+	fn flipped(options: ConvertOptions) -> [ConvertOptions; 4] {
+		[
+			options.clone(),
+			options.clone().flip_x(true),
+			options.clone().flip_y(true),
+			options.flip_x(true).flip_y(true)
+		]
+	}
+
+	#[test]
+	fn glsl_encodings_and_layouts() {
+		for encoding in [Encoding::Vec4, Encoding::PackedU32, Encoding::Palette, Encoding::Rle] {
+			for layout in [Layout::ColumnMajor, Layout::RowMajor, Layout::Flat] {
+				for options in flipped(ConvertOptions::new().encoding(encoding).layout(layout)) {
+					round_trip(&options);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn hlsl_and_wgsl_layouts() {
+		for format in [Format::Hlsl, Format::Wgsl] {
+			for layout in [Layout::ColumnMajor, Layout::RowMajor, Layout::Flat] {
+				for options in flipped(ConvertOptions::new().format(format).layout(layout).helpers(true)) {
+					round_trip(&options);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn color_conversion_is_undone() {
+		let conversion = ColorConversion {
+			color_space: ColorSpace::Linear,
+			premultiply_alpha: true
+		};
+		let image = image();
+		let options = ConvertOptions::new().color_space(ColorSpace::Linear).premultiply_alpha(true);
+		let decoded = decode(&convert(&image, &options).unwrap().source, conversion).unwrap();
+		// Colors under zero alpha are lost to the premultiplication, the rest come back within a step
+		for (decoded, original) in decoded[0].1.to_rgba8().pixels().zip(image.to_rgba8().pixels()) {
+			assert_eq!(decoded[3], original[3]);
+			if original[3] != 0 {
+				for channel in 0..3 {
+					assert!(decoded[channel].abs_diff(original[channel]) <= 1, "{:?} {:?}", decoded, original);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn combined_file() {
+		let image = image();
+		let mut source = convert(&image, &ConvertOptions::new().name("first")).unwrap().source;
+		source += &convert(&image, &ConvertOptions::new().name("second").encoding(Encoding::Rle).preamble(false)).unwrap().source[..];
+		let decoded = decode(&source, IDENTITY).unwrap();
+		assert_eq!(decoded.iter().map(|(name, _)| &name[..]).collect::<Vec<_>>(), ["first", "second"]);
+		assert!(decoded.iter().all(|(_, decoded)| decoded.to_rgba8() == image.to_rgba8()));
+	}
+
+	#[test]
+	fn legacy_file() {
+		// Written by the first versions, before the header comment, from `scoria.png`
+		let decoded = decode(include_str!("../out.glsl"), IDENTITY).unwrap();
+		let scoria = image::load_from_memory(include_bytes!("../scoria.png")).unwrap();
+		assert_eq!(decoded.len(), 1);
+		assert_eq!(decoded[0].0, "image");
+		assert_eq!(decoded[0].1.to_rgba8(), scoria.to_rgba8());
+	}
+}
@@ -0,0 +1,111 @@
+use std::io::{self, Write};
+use crate::{glsl, hlsl, pixels::Pixels, wgsl, ConvertOptions, PixelOrder};
+
+/// How each pixel is represented in the generated array
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+	/// One `vec4(r, g, b, a)` literal per pixel
+	Vec4,
+	/// One `uint` per pixel packed as 0xAARRGGBB, read through a generated `<name>Fetch` helper
+	PackedU32,
+	/// Bit-packed indices into a `vec4` palette, read through a generated `<name>Fetch` helper
+	Palette,
+	/// Runs of identical pixels per row, read through a generated `<name>Fetch` helper
+	Rle
+}
+
+impl Encoding {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Encoding::Vec4 => "vec4",
+			Encoding::PackedU32 => "packed-u32",
+			Encoding::Palette => "palette",
+			Encoding::Rle => "rle"
+		}
+	}
+
+	/// Whether the generated GLSL relies on `uint`
+	pub fn unsigned_integers(&self) -> bool {
+		matches!(self, Encoding::PackedU32 | Encoding::Palette)
+	}
+}
+
+/// Shading language the output is written in
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+	/// OpenGL Shading Language
+	Glsl,
+	/// High-Level Shading Language, as used by Direct3D
+	Hlsl,
+	/// WebGPU Shading Language, as used by wgpu
+	Wgsl
+}
+
+impl Format {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Format::Glsl => "glsl",
+			Format::Hlsl => "hlsl",
+			Format::Wgsl => "wgsl"
+		}
+	}
+}
+
+/// Position of a pixel while the image is traversed in storage order
+#[derive(Clone, Copy, Debug)]
+pub struct Position {
+	/// Index of the row, or column for `Layout::ColumnMajor`
+	pub outer: u32,
+	/// Index within the row or column
+	pub inner: u32,
+	/// Source image pixel stored at this position
+	pub x: u32,
+	pub y: u32
+}
+
+/// Writes the pixels of one image in a specific format and encoding.
+///
+/// The conversion calls `header` once, then `row_begin`, `pixel` for every pixel of the
+/// row and `row_end` for each row in the order given by the `PixelOrder`, and finally `footer`.
+pub trait Encoder {
+	/// How a shader reads the texel at `x`, `y`, e.g. `image[x][y]`
+	fn access(&self) -> String;
+
+	/// Short description of the array type and value format
+	fn format_description(&self) -> String;
+
+	/// Label and value of the minimum API version the output needs
+	fn requirement(&self) -> (&'static str, String);
+
+	/// Further labels and values worth reporting, such as the compression achieved
+	fn statistics(&self) -> Vec<(&'static str, String)> {
+		Vec::new()
+	}
+
+	fn header(&mut self, output: &mut dyn Write) -> io::Result<()>;
+
+	fn row_begin(&mut self, _output: &mut dyn Write, _row: u32) -> io::Result<()> {
+		Ok(())
+	}
+
+	fn pixel(&mut self, output: &mut dyn Write, position: Position) -> io::Result<()>;
+
+	fn row_end(&mut self, _output: &mut dyn Write, _row: u32) -> io::Result<()> {
+		Ok(())
+	}
+
+	fn footer(&mut self, output: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Encoder for the format and encoding selected in `options`
+pub fn encoder<'a>(options: &'a ConvertOptions, pixels: &'a Pixels, order: PixelOrder) -> Box<dyn Encoder + 'a> {
+	match (options.format, options.encoding) {
+		(Format::Glsl, Encoding::Vec4) => Box::new(glsl::Vec4Encoder::new(options, pixels, order)),
+		(Format::Glsl, Encoding::PackedU32) => Box::new(glsl::PackedU32Encoder::new(options, pixels, order)),
+		(Format::Glsl, Encoding::Palette) => Box::new(glsl::PaletteEncoder::new(options, pixels, order)),
+		(Format::Glsl, Encoding::Rle) => Box::new(glsl::RleEncoder::new(options, pixels, order)),
+		// Other encodings are rejected by `Conversion::new`
+		(Format::Hlsl, _) => Box::new(hlsl::Float4Encoder::new(options, pixels, order)),
+		(Format::Wgsl, _) => Box::new(wgsl::Vec4F32Encoder::new(options, pixels, order))
+	}
+}
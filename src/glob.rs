@@ -0,0 +1,191 @@
+use std::{fs, io, path::{Component, Path, PathBuf}};
+
+/// Whether `path` contains `*` or `?` wildcards
+pub fn is_pattern(path: &Path) -> bool {
+	path.to_string_lossy().contains(['*', '?'])
+}
+
+/// Files matching `pattern`, sorted by path.
+///
+/// `*` matches any run of characters within one path component, `?` a single character
+/// and a `**` component any number of nested directories. Names starting with a dot are
+/// only matched by components that start with one too. Patterns without wildcards are
+/// returned as is, so a missing file is reported when it's opened.
+pub fn expand(pattern: &Path) -> io::Result<Vec<PathBuf>> {
+	if !is_pattern(pattern) {
+		return Ok(vec![pattern.to_path_buf()]);
+	}
+
+	// An empty path stands for the current directory, so results stay relative
+	let mut candidates = vec![PathBuf::new()];
+	for component in pattern.components() {
+		match component {
+			Component::Prefix(_) | Component::RootDir | Component::ParentDir => {
+				for candidate in candidates.iter_mut() {
+					candidate.push(component.as_os_str());
+				}
+			},
+			Component::CurDir => {},
+			Component::Normal(name) => {
+				let name = name.to_string_lossy();
+				candidates = match (name.as_ref(), is_pattern(Path::new(name.as_ref()))) {
+					("**", _) => {
+						let mut directories = Vec::new();
+						for candidate in candidates {
+							subdirectories(candidate, &mut directories)?;
+						}
+						directories
+					},
+					(_, true) => {
+						let mut matched = Vec::new();
+						for candidate in candidates {
+							matching_entries(&candidate, &name, &mut matched)?;
+						}
+						matched
+					},
+					(_, false) => candidates.into_iter().map(|candidate| candidate.join(name.as_ref())).collect()
+				};
+			}
+		}
+	}
+
+	let mut files: Vec<PathBuf> = candidates.into_iter().filter(|candidate| candidate.is_file()).collect();
+	files.sort();
+	files.dedup();
+	Ok(files)
+}
+
+fn directory(path: &Path) -> &Path {
+	match path.as_os_str().is_empty() {
+		false => path,
+		true => Path::new(".")
+	}
+}
+
+/// `path` followed by every directory below it
+fn subdirectories(path: PathBuf, directories: &mut Vec<PathBuf>) -> io::Result<()> {
+	let Ok(entries) = fs::read_dir(directory(&path)) else {
+		return Ok(());
+	};
+	let mut children = Vec::new();
+	for entry in entries {
+		let entry = entry?;
+		if entry.file_type()?.is_dir() && !entry.file_name().to_string_lossy().starts_with('.') {
+			children.push(path.join(entry.file_name()));
+		}
+	}
+	children.sort();
+
+	directories.push(path);
+	for child in children {
+		subdirectories(child, directories)?;
+	}
+	Ok(())
+}
+
+/// Entries of the directory `path` whose names match the wildcard `pattern`
+fn matching_entries(path: &Path, pattern: &str, matched: &mut Vec<PathBuf>) -> io::Result<()> {
+	// Candidates that aren't directories simply have nothing to match
+	let Ok(entries) = fs::read_dir(directory(path)) else {
+		return Ok(());
+	};
+	for entry in entries {
+		let name = entry?.file_name();
+		let name = name.to_string_lossy();
+		if name.starts_with('.') && !pattern.starts_with('.') {
+			continue;
+		}
+		if matches(pattern, &name) {
+			matched.push(path.join(name.as_ref()));
+		}
+	}
+	Ok(())
+}
+
+/// Wildcard match of a whole name, backtracking to the last `*` on a mismatch
+fn matches(pattern: &str, name: &str) -> bool {
+	let pattern: Vec<char> = pattern.chars().collect();
+	let name: Vec<char> = name.chars().collect();
+	let (mut p, mut n) = (0, 0);
+	let mut star: Option<(usize, usize)> = None;
+
+	while n < name.len() {
+		match pattern.get(p) {
+			Some('*') => {
+				star = Some((p, n));
+				p += 1;
+			},
+			Some(&character) if character == '?' || character == name[n] => {
+				p += 1;
+				n += 1;
+			},
+			_ => match star {
+				// Let the `*` swallow one more character and retry
+				Some((star_p, star_n)) => {
+					star = Some((star_p, star_n + 1));
+					p = star_p + 1;
+					n = star_n + 1;
+				},
+				None => return false
+			}
+		}
+	}
+	pattern[p..].iter().all(|character| *character == '*')
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{env, fs, path::{Path, PathBuf}, process};
+	use super::{expand, matches};
+
+	#[test]
+	fn wildcards() {
+		assert!(matches("*.png", "sky.png"));
+		assert!(matches("*.png", ".png"));
+		assert!(matches("*", ""));
+		assert!(matches("sky_*_*.png", "sky_a_b_c.png"));
+		assert!(matches("*a*b", "xaxbxab"));
+		assert!(!matches("*a*b", "xaxbxa"));
+		assert!(!matches("*.png", "sky.png.bak"));
+		assert!(matches("sky?.png", "sky1.png"));
+		assert!(!matches("sky?.png", "sky.png"));
+		assert!(!matches("sky?.png", "sky12.png"));
+		assert!(matches("??", "éa"));
+		assert!(!matches("sky", "Sky"));
+	}
+
+	#[test]
+	fn expanded_files() {
+		let root = env::temp_dir().join(format!("glob_{}", process::id()));
+		for file in ["a.png", "b.png", "c.jpg", ".hidden.png", "skies/day.png", "skies/night/moon.png", ".git/x.png"] {
+			let path = root.join(file);
+			fs::create_dir_all(path.parent().unwrap()).unwrap();
+			fs::write(path, "").unwrap();
+		}
+		let matched = |pattern: &str| -> Vec<PathBuf> {
+			expand(&root.join(pattern))
+				.unwrap()
+				.into_iter()
+				.map(|path| path.strip_prefix(&root).unwrap().to_path_buf())
+				.collect()
+		};
+		let paths = |paths: &[&str]| -> Vec<PathBuf> { paths.iter().map(PathBuf::from).collect() };
+
+		assert_eq!(matched("*.png"), paths(&["a.png", "b.png"]));
+		assert_eq!(matched("?.*"), paths(&["a.png", "b.png", "c.jpg"]));
+		assert_eq!(matched(".*.png"), paths(&[".hidden.png"]));
+		assert_eq!(matched("**/*.png"), paths(&["a.png", "b.png", "skies/day.png", "skies/night/moon.png"]));
+		assert_eq!(matched("*/*.png"), paths(&["skies/day.png"]));
+		assert_eq!(matched("skies/**/moon.png"), paths(&["skies/night/moon.png"]));
+		// Directories aren't files to convert
+		assert_eq!(matched("*"), paths(&["a.png", "b.png", "c.jpg"]));
+		assert!(matched("*.gif").is_empty());
+		assert!(matched("missing/*.png").is_empty());
+		// Paths without wildcards are kept even if missing
+		assert_eq!(expand(Path::new("missing.png")).unwrap(), paths(&["missing.png"]));
+		// Relative patterns give relative paths, tests running in the crate directory
+		assert!(expand(Path::new("src/*.rs")).unwrap().contains(&PathBuf::from("src/glob.rs")));
+
+		fs::remove_dir_all(&root).unwrap();
+	}
+}
@@ -0,0 +1,164 @@
+use std::io::{self, Write};
+use crate::{animation, atlas, hash, sampling, skybox, ConvertOptions, PixelOrder};
+
+mod packed_u32;
+mod palette;
+mod rle;
+mod vec4;
+
+pub(crate) use packed_u32::PackedU32Encoder;
+pub(crate) use palette::PaletteEncoder;
+pub(crate) use rle::RleEncoder;
+pub(crate) use vec4::Vec4Encoder;
+
+/// GLSL language version the output is written for
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GlslVersion {
+	#[value(name = "120")]
+	V120,
+	#[value(name = "150")]
+	V150,
+	#[value(name = "330")]
+	V330,
+	#[value(name = "400")]
+	V400,
+	#[value(name = "420")]
+	V420,
+	#[value(name = "430")]
+	V430,
+	#[value(name = "460")]
+	V460,
+	#[value(name = "300es")]
+	Es300,
+	#[value(name = "310es")]
+	Es310,
+	#[value(name = "320es")]
+	Es320
+}
+
+impl GlslVersion {
+	pub fn is_es(&self) -> bool {
+		matches!(self, GlslVersion::Es300 | GlslVersion::Es310 | GlslVersion::Es320)
+	}
+
+	/// `#version` directive, followed by default precision statements on GLSL ES
+	pub fn preamble(&self) -> &'static str {
+		match self {
+			GlslVersion::V120 => "#version 120\n",
+			GlslVersion::V150 => "#version 150\n",
+			GlslVersion::V330 => "#version 330\n",
+			GlslVersion::V400 => "#version 400\n",
+			GlslVersion::V420 => "#version 420\n",
+			GlslVersion::V430 => "#version 430\n",
+			GlslVersion::V460 => "#version 460\n",
+			GlslVersion::Es300 => "#version 300 es\nprecision highp float;\nprecision highp int;\n",
+			GlslVersion::Es310 => "#version 310 es\nprecision highp float;\nprecision highp int;\n",
+			GlslVersion::Es320 => "#version 320 es\nprecision highp float;\nprecision highp int;\n"
+		}
+	}
+
+	/// OpenGL version that introduced this GLSL version
+	pub fn opengl_version(&self) -> &'static str {
+		match self {
+			GlslVersion::V120 => "2.1",
+			GlslVersion::V150 => "Core 3.2",
+			GlslVersion::V330 => "Core 3.3",
+			GlslVersion::V400 => "Core 4.0",
+			GlslVersion::V420 => "Core 4.2",
+			GlslVersion::V430 => "Core 4.3",
+			GlslVersion::V460 => "Core 4.6",
+			GlslVersion::Es300 => "ES 3.0",
+			GlslVersion::Es310 => "ES 3.1",
+			GlslVersion::Es320 => "ES 3.2"
+		}
+	}
+
+	/// `{ ... }` initializers, GLSL 4.20 / GL_ARB_shading_language_420pack
+	pub fn initializer_lists(&self) -> bool {
+		!self.is_es() && *self >= GlslVersion::V420
+	}
+
+	/// `image[x][y]` style arrays, GLSL 4.30 and GLSL ES 3.10
+	pub fn arrays_of_arrays(&self) -> bool {
+		match self.is_es() {
+			false => *self >= GlslVersion::V430,
+			true => *self >= GlslVersion::Es310
+		}
+	}
+
+	/// `uint` and bitwise operators, GLSL 1.30 and GLSL ES 3.00
+	pub fn unsigned_integers(&self) -> bool {
+		*self != GlslVersion::V120
+	}
+
+	/// `unpackUnorm4x8`, GLSL 4.00 and GLSL ES 3.10
+	pub fn packing_functions(&self) -> bool {
+		match self.is_es() {
+			false => *self >= GlslVersion::V400,
+			true => *self >= GlslVersion::Es310
+		}
+	}
+
+	/// Opens an array initializer, `ty` being the full array type such as `vec4[16]`
+	pub fn array_begin(&self, ty: &str) -> String {
+		match self.initializer_lists() {
+			true => String::from("{"),
+			false => format!("{}(", ty)
+		}
+	}
+
+	pub fn array_end(&self) -> &'static str {
+		match self.initializer_lists() {
+			true => "}",
+			false => ")"
+		}
+	}
+}
+
+/// `#version` line or include guard, size macros, the layout and source hash comment and any sprite or frame table
+pub(crate) fn write_prologue(
+	output: &mut dyn Write,
+	options: &ConvertOptions,
+	order: PixelOrder,
+	access: &str
+) -> io::Result<()> {
+	let macro_prefix = options.name.to_uppercase();
+	match options.include {
+		false => if options.preamble {
+			writeln!(output, "{}", options.glsl_version.preamble())?;
+		},
+		true => {
+			writeln!(output, "#ifndef {0}_GLSL\n#define {0}_GLSL\n", macro_prefix)?;
+			writeln!(
+				output,
+				"#define {0}_WIDTH {1}\n#define {0}_HEIGHT {2}\n",
+				macro_prefix,
+				order.dimensions.0,
+				order.dimensions.1
+			)?;
+		}
+	}
+	write!(output, "{}", order.header(access))?;
+	hash::write_source_hash(output, options)?;
+	writeln!(output)?;
+	atlas::write_sprites(output, options, order)?;
+	animation::write_frame_table(output, options)
+}
+
+/// Sampling, frame and skybox helpers reading through `texel` and the closing include guard
+pub(crate) fn write_epilogue(
+	output: &mut dyn Write,
+	options: &ConvertOptions,
+	order: PixelOrder,
+	texel: &str
+) -> io::Result<()> {
+	if options.helpers {
+		output.write_all(sampling::helpers(&options.name, order.dimensions, texel, options.wrap).as_bytes())?;
+	}
+	animation::write_frame_helpers(output, options, order, texel)?;
+	skybox::write_sample_function(output, options, order, texel)?;
+	if options.include {
+		output.write_all(b"\n#endif\n")?;
+	}
+	Ok(())
+}
@@ -0,0 +1,90 @@
+use std::io::{self, Write};
+use crate::{
+	encoding::{Encoder, Position},
+	glsl,
+	number::NumberFormatter,
+	pixels::Pixels,
+	ConvertOptions,
+	PixelOrder
+};
+
+/// One `uint` per pixel packed as 0xAARRGGBB, unpacked by a generated `<name>Fetch`
+pub(crate) struct PackedU32Encoder<'a> {
+	options: &'a ConvertOptions,
+	pixels: &'a Pixels,
+	order: PixelOrder,
+	formatter: NumberFormatter
+}
+
+impl<'a> PackedU32Encoder<'a> {
+	pub fn new(options: &'a ConvertOptions, pixels: &'a Pixels, order: PixelOrder) -> Self {
+		PackedU32Encoder {
+			options,
+			pixels,
+			order,
+			formatter: NumberFormatter::default()
+		}
+	}
+}
+
+impl Encoder for PackedU32Encoder<'_> {
+	fn access(&self) -> String {
+		format!("{}Fetch(ivec2(x, y))", self.options.name)
+	}
+
+	fn format_description(&self) -> String {
+		format!("uint[], packed 0xAARRGGBB, unpacked with {}Fetch", self.options.name)
+	}
+
+	fn requirement(&self) -> (&'static str, String) {
+		("Minimum OpenGL version", String::from(self.options.glsl_version.opengl_version()))
+	}
+
+	fn header(&mut self, output: &mut dyn Write) -> io::Result<()> {
+		let total_pixels = (self.order.outer_len() as u64) * (self.order.inner_len() as u64);
+
+		glsl::write_prologue(output, self.options, self.order, &self.access())?;
+		writeln!(
+			output,
+			"const uint {}[{}] = {}",
+			self.options.name,
+			total_pixels,
+			self.options.glsl_version.array_begin(&format!("uint[{}]", total_pixels))
+		)
+	}
+
+	fn row_begin(&mut self, output: &mut dyn Write, _row: u32) -> io::Result<()> {
+		output.write_all(b"\t")
+	}
+
+	fn pixel(&mut self, output: &mut dyn Write, position: Position) -> io::Result<()> {
+		output.write_all(self.formatter.packed_rgba8(self.pixels.get_rgba8(position.x, position.y)).as_bytes())?;
+		if (position.inner + 1) != self.order.inner_len() {
+			output.write_all(b", ")?;
+		}
+		Ok(())
+	}
+
+	fn row_end(&mut self, output: &mut dyn Write, row: u32) -> io::Result<()> {
+		output.write_all(match (row + 1) == self.order.outer_len() {
+			false => b",\n",
+			true => b"\n"
+		})
+	}
+
+	fn footer(&mut self, output: &mut dyn Write) -> io::Result<()> {
+		let name = &self.options.name;
+
+		writeln!(output, "{};", self.options.glsl_version.array_end())?;
+		writeln!(output, "\nvec4 {}Fetch(ivec2 coord) {{", name)?;
+		writeln!(output, "\tuint texel = {};", self.order.element(name, false, "coord.x", "coord.y"))?;
+		output.write_all(match self.options.glsl_version.packing_functions() {
+			// unpackUnorm4x8 puts the lowest byte (blue) in .x, so swizzle back to RGBA
+			true => b"\treturn unpackUnorm4x8(texel).zyxw;\n",
+			false => b"\treturn vec4((uvec4(texel) >> uvec4(16u, 8u, 0u, 24u)) & 255u) / 255.0;\n"
+		})?;
+		writeln!(output, "}}")?;
+
+		glsl::write_epilogue(output, self.options, self.order, &format!("{}Fetch(coord)", name))
+	}
+}
@@ -0,0 +1,139 @@
+use std::io::{self, Write};
+use crate::{
+	encoding::{Encoder, Position},
+	glsl,
+	number::NumberFormatter,
+	palette::Palette,
+	pixels::Pixels,
+	ConvertOptions,
+	PixelOrder
+};
+
+/// Bit-packed indices into a `vec4` palette, looked up by a generated `<name>Fetch`
+pub(crate) struct PaletteEncoder<'a> {
+	options: &'a ConvertOptions,
+	pixels: &'a Pixels,
+	order: PixelOrder,
+	formatter: NumberFormatter,
+	palette: Option<Palette>,
+	word: u32,
+	packed: u32,
+	written_words: u64
+}
+
+impl<'a> PaletteEncoder<'a> {
+	pub fn new(options: &'a ConvertOptions, pixels: &'a Pixels, order: PixelOrder) -> Self {
+		PaletteEncoder {
+			options,
+			pixels,
+			order,
+			formatter: NumberFormatter::default(),
+			palette: None,
+			word: 0,
+			packed: 0,
+			written_words: 0
+		}
+	}
+
+	fn total_words(&self, bits: u32) -> u64 {
+		((self.order.outer_len() as u64) * (self.order.inner_len() as u64)).div_ceil((32 / bits) as u64)
+	}
+}
+
+impl Encoder for PaletteEncoder<'_> {
+	fn access(&self) -> String {
+		format!("{}Fetch(ivec2(x, y))", self.options.name)
+	}
+
+	fn format_description(&self) -> String {
+		format!("uint[] + vec4[], bit-packed palette indices, looked up with {}Fetch", self.options.name)
+	}
+
+	fn requirement(&self) -> (&'static str, String) {
+		("Minimum OpenGL version", String::from(self.options.glsl_version.opengl_version()))
+	}
+
+	fn header(&mut self, output: &mut dyn Write) -> io::Result<()> {
+		let name = &self.options.name;
+		let version = self.options.glsl_version;
+		let palette = Palette::build(self.pixels, self.options.palette_size as usize);
+		let total_words = self.total_words(palette.bits_per_index());
+
+		glsl::write_prologue(output, self.options, self.order, &self.access())?;
+		writeln!(
+			output,
+			"const vec4 {}_palette[{}] = {}",
+			name,
+			palette.colors.len(),
+			version.array_begin(&format!("vec4[{}]", palette.colors.len()))
+		)?;
+		for (index, color) in palette.colors.iter().enumerate() {
+			output.write_all(b"\t")?;
+			output.write_all(self.formatter.vec4(color.map(|value| 1.0 / 255.0 * (value as f64))).as_bytes())?;
+			output.write_all(match (index + 1) == palette.colors.len() {
+				false => b",\n",
+				true => b"\n"
+			})?;
+		}
+		writeln!(output, "{};\n", version.array_end())?;
+
+		// Indices are packed from the lowest bits of each word upwards
+		writeln!(
+			output,
+			"const uint {}[{}] = {}",
+			name,
+			total_words,
+			version.array_begin(&format!("uint[{}]", total_words))
+		)?;
+
+		self.palette = Some(palette);
+		Ok(())
+	}
+
+	fn pixel(&mut self, output: &mut dyn Write, position: Position) -> io::Result<()> {
+		let palette = self.palette.as_ref().expect("header() builds the palette");
+		let bits = palette.bits_per_index();
+		let total_words = self.total_words(bits);
+		let last = (position.outer + 1) == self.order.outer_len() && (position.inner + 1) == self.order.inner_len();
+
+		self.word |= (palette.index_of(self.pixels.get_rgba8(position.x, position.y)) as u32) << (self.packed * bits);
+		self.packed += 1;
+		if self.packed == 32 / bits || last {
+			output.write_all(match self.written_words % 8 {
+				0 => b"\t",
+				_ => b" "
+			})?;
+			output.write_all(self.formatter.hex(self.word).as_bytes())?;
+			self.written_words += 1;
+			output.write_all(match (self.written_words == total_words, self.written_words % 8) {
+				(true, _) => b"\n",
+				(false, 0) => b",\n",
+				(false, _) => b","
+			})?;
+			self.word = 0;
+			self.packed = 0;
+		}
+		Ok(())
+	}
+
+	fn footer(&mut self, output: &mut dyn Write) -> io::Result<()> {
+		let name = &self.options.name;
+		let bits = self.palette.as_ref().expect("header() builds the palette").bits_per_index();
+
+		writeln!(output, "{};", self.options.glsl_version.array_end())?;
+		writeln!(output, "\nvec4 {}Fetch(ivec2 coord) {{", name)?;
+		writeln!(output, "\tint index = {};", self.order.flat_index("coord.x", "coord.y"))?;
+		writeln!(output, "\tuint word = {}[index / {}];", name, 32 / bits)?;
+		writeln!(
+			output,
+			"\treturn {}_palette[int((word >> uint((index % {}) * {})) & {}u)];",
+			name,
+			32 / bits,
+			bits,
+			(1u32 << bits) - 1
+		)?;
+		writeln!(output, "}}")?;
+
+		glsl::write_epilogue(output, self.options, self.order, &format!("{}Fetch(coord)", name))
+	}
+}
@@ -0,0 +1,199 @@
+use std::io::{self, Write};
+use crate::{
+	encoding::{Encoder, Position},
+	glsl,
+	number::NumberFormatter,
+	pixels::Pixels,
+	ConvertOptions,
+	Layout,
+	PixelOrder
+};
+
+/// Runs of identical pixels within each row (or column for `ColumnMajor`), with a row offset
+/// table so the generated `<name>Fetch` only binary-searches the runs of one row
+pub(crate) struct RleEncoder<'a> {
+	options: &'a ConvertOptions,
+	pixels: &'a Pixels,
+	order: PixelOrder,
+	formatter: NumberFormatter,
+	total_runs: u64,
+	/// Color of the run the last pixel belongs to
+	current: [f64; 4],
+	/// Index of the first run of every row, followed by the total number of runs
+	row_offsets: Vec<u64>,
+	/// Inner index each run starts at
+	run_starts: Vec<u32>
+}
+
+impl<'a> RleEncoder<'a> {
+	pub fn new(options: &'a ConvertOptions, pixels: &'a Pixels, order: PixelOrder) -> Self {
+		RleEncoder {
+			options,
+			pixels,
+			order,
+			formatter: NumberFormatter::default(),
+			total_runs: 0,
+			current: [0.0; 4],
+			row_offsets: Vec::new(),
+			run_starts: Vec::new()
+		}
+	}
+
+	fn count_runs(&self) -> u64 {
+		let mut runs = 0;
+		for outer in 0..self.order.outer_len() {
+			let mut current = None;
+			for inner in 0..self.order.inner_len() {
+				let (x, y) = self.order.source(outer, inner);
+				let color = Some(self.pixels.get(x, y));
+				if color != current {
+					runs += 1;
+					current = color;
+				}
+			}
+		}
+		runs
+	}
+
+	/// Writes `values` as an `int` array, starting a new line at each of `line_breaks`
+	fn write_table(&self, output: &mut dyn Write, name: &str, values: &[u64], line_breaks: &[u64]) -> io::Result<()> {
+		let version = self.options.glsl_version;
+		writeln!(
+			output,
+			"\nconst int {}[{}] = {}",
+			name,
+			values.len(),
+			version.array_begin(&format!("int[{}]", values.len()))
+		)?;
+		for line in line_breaks.windows(2) {
+			let values = &values[(line[0] as usize)..(line[1] as usize)];
+			if values.is_empty() {
+				continue;
+			}
+			output.write_all(b"\t")?;
+			for (index, value) in values.iter().enumerate() {
+				write!(output, "{}", value)?;
+				if (index + 1) != values.len() {
+					output.write_all(b", ")?;
+				}
+			}
+			output.write_all(match line[1] == *line_breaks.last().unwrap() {
+				false => b",\n",
+				true => b"\n"
+			})?;
+		}
+		writeln!(output, "{};", version.array_end())
+	}
+}
+
+impl Encoder for RleEncoder<'_> {
+	fn access(&self) -> String {
+		format!("{}Fetch(ivec2(x, y))", self.options.name)
+	}
+
+	fn format_description(&self) -> String {
+		format!(
+			"vec4[] + int[], run-length encoded {}, looked up with {}Fetch",
+			match self.order.layout {
+				Layout::ColumnMajor => "columns",
+				_ => "rows"
+			},
+			self.options.name
+		)
+	}
+
+	fn requirement(&self) -> (&'static str, String) {
+		("Minimum OpenGL version", String::from(self.options.glsl_version.opengl_version()))
+	}
+
+	fn statistics(&self) -> Vec<(&'static str, String)> {
+		let total_pixels = (self.order.outer_len() as u64) * (self.order.inner_len() as u64);
+		let runs = self.count_runs();
+		// A vec4 takes 16 bytes and an int 4, each run has a color and a start
+		let plain_bytes = total_pixels * 16;
+		let rle_bytes = runs * 20 + ((self.order.outer_len() as u64) + 1) * 4;
+		vec![(
+			"Compression",
+			format!(
+				"{} runs, {} bytes versus {} as vec4, {:.2}:1",
+				runs,
+				rle_bytes,
+				plain_bytes,
+				(plain_bytes as f64) / (rle_bytes as f64)
+			)
+		)]
+	}
+
+	fn header(&mut self, output: &mut dyn Write) -> io::Result<()> {
+		self.total_runs = self.count_runs();
+
+		glsl::write_prologue(output, self.options, self.order, &self.access())?;
+		writeln!(
+			output,
+			"const vec4 {}[{}] = {}",
+			self.options.name,
+			self.total_runs,
+			self.options.glsl_version.array_begin(&format!("vec4[{}]", self.total_runs))
+		)
+	}
+
+	fn row_begin(&mut self, output: &mut dyn Write, _row: u32) -> io::Result<()> {
+		self.row_offsets.push(self.run_starts.len() as u64);
+		output.write_all(b"\t")
+	}
+
+	fn pixel(&mut self, output: &mut dyn Write, position: Position) -> io::Result<()> {
+		let color = self.pixels.get(position.x, position.y);
+		if position.inner == 0 || color != self.current {
+			if position.inner != 0 {
+				output.write_all(b", ")?;
+			}
+			output.write_all(self.formatter.vec4(color).as_bytes())?;
+			self.run_starts.push(position.inner);
+			self.current = color;
+		}
+		Ok(())
+	}
+
+	fn row_end(&mut self, output: &mut dyn Write, row: u32) -> io::Result<()> {
+		output.write_all(match (row + 1) == self.order.outer_len() {
+			false => b",\n",
+			true => b"\n"
+		})
+	}
+
+	fn footer(&mut self, output: &mut dyn Write) -> io::Result<()> {
+		let name = &self.options.name;
+		let version = self.options.glsl_version;
+
+		writeln!(output, "{};", version.array_end())?;
+		self.row_offsets.push(self.run_starts.len() as u64);
+
+		let run_starts: Vec<u64> = self.run_starts.iter().map(|start| *start as u64).collect();
+		self.write_table(output, &format!("{}_run_starts", name), &run_starts, &self.row_offsets)?;
+		// Eight offsets per line rather than one line per row
+		let line_breaks: Vec<u64> = (0..self.row_offsets.len() as u64)
+			.step_by(8)
+			.chain([self.row_offsets.len() as u64])
+			.collect();
+		self.write_table(output, &format!("{}_rows", name), &self.row_offsets, &line_breaks)?;
+
+		let (outer, inner) = self.order.outer_inner("coord.x", "coord.y");
+		writeln!(output, "\nvec4 {}Fetch(ivec2 coord) {{", name)?;
+		writeln!(output, "\tint low = {}_rows[{}];", name, outer)?;
+		writeln!(output, "\tint high = {}_rows[{} + 1] - 1;", name, outer)?;
+		// Last run of the row starting at or before the texel
+		writeln!(output, "\twhile (low < high) {{")?;
+		writeln!(output, "\t\tint middle = (low + high + 1) / 2;")?;
+		writeln!(output, "\t\tif ({}_run_starts[middle] <= {}) {{", name, inner)?;
+		writeln!(output, "\t\t\tlow = middle;")?;
+		writeln!(output, "\t\t}} else {{")?;
+		writeln!(output, "\t\t\thigh = middle - 1;")?;
+		writeln!(output, "\t\t}}")?;
+		writeln!(output, "\t}}")?;
+		writeln!(output, "\treturn {}[low];", name)?;
+		writeln!(output, "}}")?;
+
+		glsl::write_epilogue(output, self.options, self.order, &format!("{}Fetch(coord)", name))
+	}
+}
@@ -0,0 +1,108 @@
+use std::io::{self, Write};
+use crate::{
+	encoding::{Encoder, Position},
+	glsl,
+	number::NumberFormatter,
+	pixels::Pixels,
+	ConvertOptions,
+	Layout,
+	PixelOrder
+};
+
+/// One `vec4` literal per pixel, nested as `[outer][inner]` where arrays of arrays are available
+pub(crate) struct Vec4Encoder<'a> {
+	options: &'a ConvertOptions,
+	pixels: &'a Pixels,
+	order: PixelOrder,
+	nested: bool,
+	formatter: NumberFormatter
+}
+
+impl<'a> Vec4Encoder<'a> {
+	pub fn new(options: &'a ConvertOptions, pixels: &'a Pixels, order: PixelOrder) -> Self {
+		Vec4Encoder {
+			options,
+			pixels,
+			order,
+			nested: options.glsl_version.arrays_of_arrays() && order.layout != Layout::Flat,
+			formatter: NumberFormatter::default()
+		}
+	}
+}
+
+impl Encoder for Vec4Encoder<'_> {
+	fn access(&self) -> String {
+		self.order.element(&self.options.name, self.nested, "x", "y")
+	}
+
+	fn format_description(&self) -> String {
+		format!(
+			"{}, {}",
+			match self.nested {
+				true => "vec4[][]",
+				false => "vec4[]"
+			},
+			self.pixels.value_format()
+		)
+	}
+
+	fn requirement(&self) -> (&'static str, String) {
+		("Minimum OpenGL version", String::from(self.options.glsl_version.opengl_version()))
+	}
+
+	fn header(&mut self, output: &mut dyn Write) -> io::Result<()> {
+		let (outer_len, inner_len) = (self.order.outer_len(), self.order.inner_len());
+		let version = self.options.glsl_version;
+
+		glsl::write_prologue(output, self.options, self.order, &self.access())?;
+		match self.nested {
+			true => writeln!(
+				output,
+				"const vec4 {0}[{1}][{2}] = {3}",
+				self.options.name,
+				outer_len,
+				inner_len,
+				version.array_begin(&format!("vec4[{}][{}]", outer_len, inner_len))
+			),
+			false => writeln!(
+				output,
+				"const vec4 {0}[{1}] = {2}",
+				self.options.name,
+				(outer_len as u64) * (inner_len as u64),
+				version.array_begin(&format!("vec4[{}]", (outer_len as u64) * (inner_len as u64)))
+			)
+		}
+	}
+
+	fn row_begin(&mut self, output: &mut dyn Write, _row: u32) -> io::Result<()> {
+		output.write_all(b"\t")?;
+		if self.nested {
+			let version = self.options.glsl_version;
+			output.write_all(version.array_begin(&format!("vec4[{}]", self.order.inner_len())).as_bytes())?;
+		}
+		Ok(())
+	}
+
+	fn pixel(&mut self, output: &mut dyn Write, position: Position) -> io::Result<()> {
+		output.write_all(self.formatter.vec4(self.pixels.get(position.x, position.y)).as_bytes())?;
+		if (position.inner + 1) != self.order.inner_len() {
+			output.write_all(b", ")?;
+		} else if self.nested {
+			output.write_all(self.options.glsl_version.array_end().as_bytes())?;
+		}
+		Ok(())
+	}
+
+	fn row_end(&mut self, output: &mut dyn Write, row: u32) -> io::Result<()> {
+		output.write_all(match (row + 1) == self.order.outer_len() {
+			false => b",\n",
+			true => b"\n"
+		})
+	}
+
+	fn footer(&mut self, output: &mut dyn Write) -> io::Result<()> {
+		writeln!(output, "{};", self.options.glsl_version.array_end())?;
+		let texel = self.order.element(&self.options.name, self.nested, "coord.x", "coord.y");
+		glsl::write_epilogue(output, self.options, self.order, &texel)
+	}
+}
@@ -0,0 +1,119 @@
+use std::{
+	fs::{self, File},
+	hash::Hasher,
+	io::{self, BufRead, BufReader, Write},
+	path::Path
+};
+use crate::ConvertOptions;
+
+const OFFSET_BASIS: u64 = 0xCBF29CE484222325;
+const PRIME: u64 = 0x100000001B3;
+
+/// 64-bit FNV-1a, whose values stay the same across Rust versions and platforms unlike those
+/// of `DefaultHasher`. Integers written through `Hasher` use native byte order, so hashes
+/// meant to be stored should only be fed bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+	fn default() -> Self {
+		Fnv1a(OFFSET_BASIS)
+	}
+}
+
+impl Hasher for Fnv1a {
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 = (self.0 ^ (*byte as u64)).wrapping_mul(PRIME);
+		}
+	}
+
+	fn finish(&self) -> u64 {
+		self.0
+	}
+}
+
+/// Hash of `settings`, the names and content of `inputs` and the version of this crate
+pub(crate) fn source_hash(settings: &str, inputs: &[impl AsRef<Path>]) -> io::Result<u64> {
+	let mut hasher = Fnv1a::default();
+	hasher.write(env!("CARGO_PKG_VERSION").as_bytes());
+	hasher.write(settings.as_bytes());
+	for input in inputs {
+		let input = input.as_ref();
+		let content = fs::read(input)?;
+		hasher.write(input.file_name().unwrap_or_default().to_string_lossy().as_bytes());
+		hasher.write(&(content.len() as u64).to_le_bytes());
+		hasher.write(&content);
+	}
+	Ok(hasher.finish())
+}
+
+/// Start of the header comment line recording `ConvertOptions::source_hash`
+const SOURCE_HASH_PREFIX: &str = "// Source hash: ";
+
+/// Line recording the hash of the inputs and options an output was made from, if one was given
+pub(crate) fn write_source_hash(output: &mut dyn Write, options: &ConvertOptions) -> io::Result<()> {
+	match options.source_hash {
+		Some(hash) => writeln!(output, "{}{:016x}", SOURCE_HASH_PREFIX, hash),
+		None => Ok(())
+	}
+}
+
+/// Hash recorded in the header comment of generated source, only reading up to the first
+/// line that isn't a comment, preprocessor directive or GLSL ES default precision statement
+pub fn read_source_hash(source: impl BufRead) -> Option<u64> {
+	for line in source.lines() {
+		let line = line.ok()?;
+		let line = line.trim();
+		if let Some(hash) = line.strip_prefix(SOURCE_HASH_PREFIX) {
+			return u64::from_str_radix(hash, 16).ok();
+		}
+		if !(line.is_empty() || line.starts_with("//") || line.starts_with('#') || line.starts_with("precision ")) {
+			return None;
+		}
+	}
+	None
+}
+
+/// Whether the file at `path` records `hash` as its source hash, so converting again would give
+/// the same file and can be skipped
+pub fn is_up_to_date(path: &Path, hash: u64) -> bool {
+	File::open(path).ok().and_then(|file| read_source_hash(BufReader::new(file))) == Some(hash)
+}
+
+#[cfg(test)]
+mod tests {
+	use image::DynamicImage;
+	use crate::{convert, ConvertOptions, Format, GlslVersion};
+	use super::read_source_hash;
+
+	const HASH: u64 = 0x0123456789ABCDEF;
+
+	/// Hash read back from the output of converting a small image with `options`
+	fn round_trip(options: ConvertOptions) -> Option<u64> {
+		let source = convert(&DynamicImage::new_rgba8(2, 2), &options.helpers(true).source_hash(Some(HASH))).unwrap().source;
+		read_source_hash(source.as_bytes())
+	}
+
+	#[test]
+	fn read_back() {
+		assert_eq!(round_trip(ConvertOptions::new()), Some(HASH));
+		// `#version 300 es` is followed by the precision statements before the hash
+		assert_eq!(round_trip(ConvertOptions::new().glsl_version(GlslVersion::Es300)), Some(HASH));
+		// The include guard and size macros come first
+		assert_eq!(round_trip(ConvertOptions::new().include(true)), Some(HASH));
+		assert_eq!(round_trip(ConvertOptions::new().format(Format::Hlsl)), Some(HASH));
+		assert_eq!(round_trip(ConvertOptions::new().format(Format::Hlsl).include(true)), Some(HASH));
+		assert_eq!(round_trip(ConvertOptions::new().format(Format::Wgsl)), Some(HASH));
+	}
+
+	#[test]
+	fn not_found() {
+		let source = convert(&DynamicImage::new_rgba8(2, 2), &ConvertOptions::new()).unwrap().source;
+		assert_eq!(read_source_hash(source.as_bytes()), None);
+		// Only the header is read, not hashes further down
+		assert_eq!(read_source_hash("#version 430\nconst int a = 1;\n// Source hash: 0123456789abcdef\n".as_bytes()), None);
+		assert_eq!(read_source_hash("// Source hash: not hex\n".as_bytes()), None);
+		assert_eq!(read_source_hash("".as_bytes()), None);
+	}
+}
@@ -0,0 +1,166 @@
+use std::io::{self, Write};
+use crate::{
+	animation,
+	atlas,
+	encoding::{Encoder, Position},
+	hash,
+	number::NumberFormatter,
+	pixels::Pixels,
+	skybox,
+	ConvertOptions,
+	Layout,
+	PixelOrder,
+	WrapMode
+};
+
+/// One `float4` literal per pixel, nested as `[outer][inner]` unless the layout is flat
+pub(crate) struct Float4Encoder<'a> {
+	options: &'a ConvertOptions,
+	pixels: &'a Pixels,
+	order: PixelOrder,
+	nested: bool,
+	formatter: NumberFormatter
+}
+
+impl<'a> Float4Encoder<'a> {
+	pub fn new(options: &'a ConvertOptions, pixels: &'a Pixels, order: PixelOrder) -> Self {
+		Float4Encoder {
+			options,
+			pixels,
+			order,
+			nested: order.layout != Layout::Flat,
+			formatter: NumberFormatter::default()
+		}
+	}
+}
+
+impl Encoder for Float4Encoder<'_> {
+	fn access(&self) -> String {
+		self.order.element(&self.options.name, self.nested, "x", "y")
+	}
+
+	fn format_description(&self) -> String {
+		format!(
+			"{}, {}",
+			match self.nested {
+				true => "float4[][]",
+				false => "float4[]"
+			},
+			self.pixels.value_format()
+		)
+	}
+
+	fn requirement(&self) -> (&'static str, String) {
+		// Integer coordinates in the helpers need shader model 4
+		("Minimum shader model", String::from("4.0"))
+	}
+
+	fn header(&mut self, output: &mut dyn Write) -> io::Result<()> {
+		let (outer_len, inner_len) = (self.order.outer_len(), self.order.inner_len());
+		let name = &self.options.name;
+
+		if self.options.include {
+			let macro_prefix = name.to_uppercase();
+			writeln!(output, "#ifndef {0}_HLSL\n#define {0}_HLSL\n", macro_prefix)?;
+			writeln!(
+				output,
+				"#define {0}_WIDTH {1}\n#define {0}_HEIGHT {2}\n",
+				macro_prefix,
+				self.order.dimensions.0,
+				self.order.dimensions.1
+			)?;
+		}
+		write!(output, "{}", self.order.header(&self.access()))?;
+		hash::write_source_hash(output, self.options)?;
+		writeln!(output)?;
+		atlas::write_sprites(output, self.options, self.order)?;
+		animation::write_frame_table(output, self.options)?;
+		match self.nested {
+			true => writeln!(output, "static const float4 {}[{}][{}] = {{", name, outer_len, inner_len),
+			false => writeln!(output, "static const float4 {}[{}] = {{", name, (outer_len as u64) * (inner_len as u64))
+		}
+	}
+
+	fn row_begin(&mut self, output: &mut dyn Write, _row: u32) -> io::Result<()> {
+		output.write_all(match self.nested {
+			true => b"\t{",
+			false => b"\t"
+		})
+	}
+
+	fn pixel(&mut self, output: &mut dyn Write, position: Position) -> io::Result<()> {
+		output.write_all(self.formatter.float4(self.pixels.get(position.x, position.y)).as_bytes())?;
+		if (position.inner + 1) != self.order.inner_len() {
+			output.write_all(b", ")?;
+		} else if self.nested {
+			output.write_all(b"}")?;
+		}
+		Ok(())
+	}
+
+	fn row_end(&mut self, output: &mut dyn Write, row: u32) -> io::Result<()> {
+		output.write_all(match (row + 1) == self.order.outer_len() {
+			false => b",\n",
+			true => b"\n"
+		})
+	}
+
+	fn footer(&mut self, output: &mut dyn Write) -> io::Result<()> {
+		writeln!(output, "}};")?;
+		let texel = self.order.element(&self.options.name, self.nested, "coord.x", "coord.y");
+		if self.options.helpers {
+			output.write_all(helpers(&self.options.name, self.order.dimensions, &texel, self.options.wrap).as_bytes())?;
+		}
+		animation::write_frame_helpers(output, self.options, self.order, &texel)?;
+		skybox::write_sample_function(output, self.options, self.order, &texel)?;
+		if self.options.include {
+			output.write_all(b"\n#endif\n")?;
+		}
+		Ok(())
+	}
+}
+
+/// HLSL counterpart of `sampling::helpers`, reading the in-range texel at `int2 coord` through `texel`
+fn helpers(name: &str, dimensions: (u32, u32), texel: &str, wrap: WrapMode) -> String {
+	let mut output = String::new();
+
+	output += &format!("\nstatic const int2 {}_size = int2({}, {});\n", name, dimensions.0, dimensions.1)[..];
+
+	// Integer `%` keeps the sign of negative coordinates, so repeating goes through `floor` on floats
+	output += &format!("\nint2 {}_wrap(int2 coord) {{\n", name)[..];
+	output += &match wrap {
+		WrapMode::Clamp => format!("\treturn clamp(coord, int2(0, 0), {}_size - 1);\n", name),
+		WrapMode::Repeat => format!("\treturn coord - {0}_size * (int2)floor((float2)coord / (float2){0}_size);\n", name),
+		WrapMode::Mirror => format!(
+			concat!(
+				"\tint2 period = {}_size * 2;\n",
+				"\tint2 wrapped = coord - period * (int2)floor((float2)coord / (float2)period);\n",
+				"\treturn min(wrapped, period - 1 - wrapped);\n"
+			),
+			name
+		)
+	}[..];
+	output += "}\n";
+
+	output += &format!("\nfloat4 {}_fetch(int2 coord) {{\n", name)[..];
+	output += &format!("\tcoord = {}_wrap(coord);\n", name)[..];
+	output += &format!("\treturn {};\n", texel)[..];
+	output += "}\n";
+
+	output += &format!("\nfloat4 {}_sample_nearest(float2 uv) {{\n", name)[..];
+	output += &format!("\treturn {0}_fetch((int2)floor(uv * (float2){0}_size));\n", name)[..];
+	output += "}\n";
+
+	output += &format!("\nfloat4 {}_sample_bilinear(float2 uv) {{\n", name)[..];
+	output += &format!("\tfloat2 position = uv * (float2){}_size - 0.5;\n", name)[..];
+	output += "\tint2 base = (int2)floor(position);\n";
+	output += "\tfloat2 weight = frac(position);\n";
+	output += "\treturn lerp(\n";
+	output += &format!("\t\tlerp({0}_fetch(base), {0}_fetch(base + int2(1, 0)), weight.x),\n", name)[..];
+	output += &format!("\t\tlerp({0}_fetch(base + int2(0, 1)), {0}_fetch(base + int2(1, 1)), weight.x),\n", name)[..];
+	output += "\t\tweight.y\n";
+	output += "\t);\n";
+	output += "}\n";
+
+	output
+}
@@ -0,0 +1,98 @@
+/// Order the pixels are stored in
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+	/// `image[x][y]`, one column after another
+	ColumnMajor,
+	/// `image[y][x]`, one row after another
+	RowMajor,
+	/// `image[y * width + x]`, a single array of rows
+	Flat
+}
+
+/// Maps between positions in the generated array and pixels of the source image
+#[derive(Clone, Copy, Debug)]
+pub struct PixelOrder {
+	pub layout: Layout,
+	pub flip_x: bool,
+	pub flip_y: bool,
+	pub dimensions: (u32, u32)
+}
+
+impl PixelOrder {
+	/// Number of columns for `ColumnMajor`, rows otherwise
+	pub fn outer_len(&self) -> u32 {
+		match self.layout {
+			Layout::ColumnMajor => self.dimensions.0,
+			Layout::RowMajor | Layout::Flat => self.dimensions.1
+		}
+	}
+
+	/// Number of pixels in a column for `ColumnMajor`, in a row otherwise
+	pub fn inner_len(&self) -> u32 {
+		match self.layout {
+			Layout::ColumnMajor => self.dimensions.1,
+			Layout::RowMajor | Layout::Flat => self.dimensions.0
+		}
+	}
+
+	/// Source image pixel stored at `[outer][inner]`
+	pub fn source(&self, outer: u32, inner: u32) -> (u32, u32) {
+		let (x, y) = match self.layout {
+			Layout::ColumnMajor => (outer, inner),
+			Layout::RowMajor | Layout::Flat => (inner, outer)
+		};
+		(
+			match self.flip_x {
+				false => x,
+				true => self.dimensions.0 - 1 - x
+			},
+			match self.flip_y {
+				false => y,
+				true => self.dimensions.1 - 1 - y
+			}
+		)
+	}
+
+	/// Index into the array flattened to one dimension, for GLSL expressions `x` and `y`
+	pub fn flat_index(&self, x: &str, y: &str) -> String {
+		match self.layout {
+			Layout::ColumnMajor => format!("{} * {} + {}", x, self.dimensions.1, y),
+			Layout::RowMajor | Layout::Flat => format!("{} * {} + {}", y, self.dimensions.0, x)
+		}
+	}
+
+	/// Outer and inner index for GLSL expressions `x` and `y`
+	pub fn outer_inner<'s>(&self, x: &'s str, y: &'s str) -> (&'s str, &'s str) {
+		match self.layout {
+			Layout::ColumnMajor => (x, y),
+			Layout::RowMajor | Layout::Flat => (y, x)
+		}
+	}
+
+	/// Element access into `name`, using `[outer][inner]` when `nested` and the layout allows it
+	pub fn element(&self, name: &str, nested: bool, x: &str, y: &str) -> String {
+		match (self.layout, nested) {
+			(Layout::ColumnMajor, true) => format!("{}[{}][{}]", name, x, y),
+			(Layout::RowMajor, true) => format!("{}[{}][{}]", name, y, x),
+			_ => format!("{}[{}]", name, self.flat_index(x, y))
+		}
+	}
+
+	/// Comment lines describing how the array is addressed
+	pub fn header(&self, access: &str) -> String {
+		format!(
+			"// {} x {} texels, read as {}\n// x = 0 is the {} column and y = 0 the {} row of the source image\n",
+			self.dimensions.0,
+			self.dimensions.1,
+			access,
+			match self.flip_x {
+				false => "left",
+				true => "right"
+			},
+			match self.flip_y {
+				false => "top",
+				true => "bottom"
+			}
+		)
+	}
+}
@@ -0,0 +1,400 @@
+//! Converts images into GLSL, HLSL or WGSL arrays, so they can be drawn by shaders without a texture,
+//! and reads generated arrays back into images with `decode`.
+//!
+//! ```no_run
+//! use image_to_glsl_array::{convert, ConvertOptions, Encoding};
+//!
+//! let image = image::open("sky.png").unwrap();
+//! let options = ConvertOptions::new().name("sky").encoding(Encoding::PackedU32).include(true);
+//! std::fs::write("sky.glsl", convert(&image, &options).unwrap().source).unwrap();
+//! ```
+
+use std::{
+	fmt::Debug,
+	io::{self, Write},
+	path::Path
+};
+use image::DynamicImage;
+use thiserror::Error;
+
+pub mod animation;
+pub mod atlas;
+pub mod color;
+pub mod decode;
+pub mod encoding;
+pub mod glob;
+pub mod glsl;
+pub mod hash;
+mod hlsl;
+pub mod layout;
+pub mod manifest;
+pub mod name;
+mod number;
+pub mod output;
+mod palette;
+pub mod pixels;
+pub mod sampling;
+pub mod skybox;
+pub mod transform;
+mod wgsl;
+
+pub use animation::Animation;
+pub use atlas::{Atlas, Sprite};
+pub use color::{ColorConversion, ColorSpace};
+pub use decode::{decode, DecodeError};
+pub use encoding::{Encoder, Encoding, Format, Position};
+pub use glsl::GlslVersion;
+pub use layout::{Layout, PixelOrder};
+pub use name::NameError;
+pub use output::PendingOutput;
+pub use pixels::Pixels;
+pub use sampling::WrapMode;
+pub use skybox::Projection;
+pub use transform::{Transform, TransformError};
+
+#[derive(Error, Debug)]
+pub enum ConvertError {
+	#[error(transparent)]
+	Name(#[from] NameError),
+	#[error("The {0} encoding needs unsigned integers, which require GLSL 1.30 or newer")]
+	UnsupportedEncoding(&'static str),
+	#[error("Include guards aren't available for {0} output, it has no preprocessor")]
+	UnavailableInclude(&'static str),
+	#[error("The {encoding} encoding isn't available for {format} output")]
+	UnavailableEncoding {
+		encoding: &'static str,
+		format: &'static str
+	},
+	#[error("Sprite {name} can't be used, {problem}")]
+	InvalidSprite {
+		name: String,
+		problem: &'static str
+	},
+	#[error("An image {height} pixels tall can't be split into {frames} equally tall frames")]
+	FrameCount {
+		height: u32,
+		frames: usize
+	},
+	#[error("Can't build a skybox, {0}")]
+	Skybox(&'static str),
+	#[error(transparent)]
+	Io(#[from] io::Error)
+}
+
+/// Settings for a conversion, built up from `ConvertOptions::new()`
+#[derive(Clone, Debug)]
+pub struct ConvertOptions {
+	name: String,
+	format: Format,
+	encoding: Encoding,
+	palette_size: u16,
+	layout: Option<Layout>,
+	flip_x: bool,
+	flip_y: bool,
+	color_space: ColorSpace,
+	premultiply_alpha: bool,
+	helpers: bool,
+	wrap: WrapMode,
+	glsl_version: GlslVersion,
+	include: bool,
+	preamble: bool,
+	sprites: Vec<Sprite>,
+	frame_durations: Vec<u32>,
+	skybox: Option<Projection>,
+	source_hash: Option<u64>
+}
+
+impl Default for ConvertOptions {
+	fn default() -> Self {
+		ConvertOptions {
+			name: String::from("image"),
+			format: Format::Glsl,
+			encoding: Encoding::Vec4,
+			palette_size: 256,
+			layout: None,
+			flip_x: false,
+			flip_y: false,
+			color_space: ColorSpace::Srgb,
+			premultiply_alpha: false,
+			helpers: false,
+			wrap: WrapMode::Clamp,
+			glsl_version: GlslVersion::V430,
+			include: false,
+			preamble: true,
+			sprites: Vec::new(),
+			frame_durations: Vec::new(),
+			skybox: None,
+			source_hash: None
+		}
+	}
+}
+
+impl ConvertOptions {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Identifier of the generated array, also used to prefix helpers and macros
+	pub fn name(mut self, name: impl Into<String>) -> Self {
+		self.name = name.into();
+		self
+	}
+
+	/// Shading language to write
+	pub fn format(mut self, format: Format) -> Self {
+		self.format = format;
+		self
+	}
+
+	pub fn encoding(mut self, encoding: Encoding) -> Self {
+		self.encoding = encoding;
+		self
+	}
+
+	/// Maximum number of colors for `Encoding::Palette`, clamped to 1..=256
+	pub fn palette_size(mut self, palette_size: u16) -> Self {
+		self.palette_size = palette_size.clamp(1, 256);
+		self
+	}
+
+	/// Storage order, defaults to `ColumnMajor` for GLSL and `RowMajor` for HLSL and WGSL
+	pub fn layout(mut self, layout: Layout) -> Self {
+		self.layout = Some(layout);
+		self
+	}
+
+	pub fn flip_x(mut self, flip_x: bool) -> Self {
+		self.flip_x = flip_x;
+		self
+	}
+
+	pub fn flip_y(mut self, flip_y: bool) -> Self {
+		self.flip_y = flip_y;
+		self
+	}
+
+	pub fn color_space(mut self, color_space: ColorSpace) -> Self {
+		self.color_space = color_space;
+		self
+	}
+
+	pub fn premultiply_alpha(mut self, premultiply_alpha: bool) -> Self {
+		self.premultiply_alpha = premultiply_alpha;
+		self
+	}
+
+	/// Also generate the `<name>_fetch` and `<name>_sample_*` functions
+	pub fn helpers(mut self, helpers: bool) -> Self {
+		self.helpers = helpers;
+		self
+	}
+
+	pub fn wrap(mut self, wrap: WrapMode) -> Self {
+		self.wrap = wrap;
+		self
+	}
+
+	pub fn glsl_version(mut self, glsl_version: GlslVersion) -> Self {
+		self.glsl_version = glsl_version;
+		self
+	}
+
+	/// Leave out `#version` and wrap the output in include guards
+	pub fn include(mut self, include: bool) -> Self {
+		self.include = include;
+		self
+	}
+
+	/// Write the `#version` line, turned off when appending to a file that already starts with one
+	pub fn preamble(mut self, preamble: bool) -> Self {
+		self.preamble = preamble;
+		self
+	}
+
+	/// Sub-images to emit index constants and a `<name>_rects` table for, as from `Atlas::pack`
+	pub fn sprites(mut self, sprites: Vec<Sprite>) -> Self {
+		self.sprites = sprites;
+		self
+	}
+
+	/// Milliseconds each frame is shown for, the image being the frames stacked top to bottom
+	/// as from `Animation::decode`
+	pub fn frame_durations(mut self, frame_durations: Vec<u32>) -> Self {
+		self.frame_durations = frame_durations;
+		self
+	}
+
+	/// Treats the image as a skybox in `projection`, adding a `<name>_sample` function that
+	/// reads the texel seen in a direction
+	pub fn skybox(mut self, projection: Option<Projection>) -> Self {
+		self.skybox = projection;
+		self
+	}
+
+	/// Hash of the inputs and options the output is made from, as from `source_hash_of`, recorded
+	/// in the header comment so `hash::is_up_to_date` can tell whether converting again is needed
+	pub fn source_hash(mut self, hash: Option<u64>) -> Self {
+		self.source_hash = hash;
+		self
+	}
+
+	/// Hash of these options, the names and content of `inputs` and the version of this crate, plus
+	/// `settings` for anything else the output depends on, such as a `Transform` applied beforehand
+	pub fn source_hash_of(&self, inputs: &[impl AsRef<Path>], settings: impl Debug) -> io::Result<u64> {
+		// Sprites and frame durations are read from the inputs, which are hashed themselves
+		let options = ConvertOptions {
+			sprites: Vec::new(),
+			frame_durations: Vec::new(),
+			source_hash: None,
+			..self.clone()
+		};
+		hash::source_hash(&format!("{:?} {:?}", options, settings), inputs)
+	}
+}
+
+/// Generated shader source
+#[derive(Clone, Debug)]
+pub struct GlslOutput {
+	pub name: String,
+	pub dimensions: (u32, u32),
+	pub source: String
+}
+
+/// An image prepared for conversion with validated options
+pub struct Conversion<'a> {
+	options: &'a ConvertOptions,
+	pixels: Pixels,
+	order: PixelOrder
+}
+
+impl<'a> Conversion<'a> {
+	pub fn new(image: &DynamicImage, options: &'a ConvertOptions) -> Result<Self, ConvertError> {
+		name::validate(&options.name)?;
+		if options.format == Format::Glsl && options.encoding.unsigned_integers() && !options.glsl_version.unsigned_integers() {
+			return Err(ConvertError::UnsupportedEncoding(options.encoding.name()));
+		}
+		if options.format != Format::Glsl && options.encoding != Encoding::Vec4 {
+			return Err(ConvertError::UnavailableEncoding {
+				encoding: options.encoding.name(),
+				format: options.format.name()
+			});
+		}
+		if options.format == Format::Wgsl && options.include {
+			return Err(ConvertError::UnavailableInclude(options.format.name()));
+		}
+
+		let pixels = Pixels::from_image(
+			image,
+			ColorConversion {
+				color_space: options.color_space,
+				premultiply_alpha: options.premultiply_alpha
+			}
+		);
+		let order = PixelOrder {
+			layout: options.layout.unwrap_or(match options.format {
+				Format::Glsl => Layout::ColumnMajor,
+				Format::Hlsl | Format::Wgsl => Layout::RowMajor
+			}),
+			flip_x: options.flip_x,
+			flip_y: options.flip_y,
+			dimensions: pixels.dimensions()
+		};
+		atlas::validate(&options.sprites, options.format, order.dimensions)?;
+		animation::validate(&options.frame_durations, order.dimensions)?;
+		skybox::validate(options.skybox, order.dimensions)?;
+
+		Ok(Conversion { options, pixels, order })
+	}
+
+	/// Identifier of the generated array
+	pub fn name(&self) -> &str {
+		&self.options.name
+	}
+
+	/// Milliseconds per frame, empty unless the image is an animation
+	pub fn frame_durations(&self) -> &[u32] {
+		&self.options.frame_durations
+	}
+
+	/// Projection of the image if it's a skybox
+	pub fn skybox(&self) -> Option<Projection> {
+		self.options.skybox
+	}
+
+	pub fn dimensions(&self) -> (u32, u32) {
+		self.order.dimensions
+	}
+
+	/// Prefix of the generated `#define`s, the uppercased name
+	pub fn macro_prefix(&self) -> String {
+		self.options.name.to_uppercase()
+	}
+
+	/// Macro guarding against repeated `#include`s, e.g. `IMAGE_GLSL`
+	pub fn include_guard(&self) -> String {
+		format!("{}_{}", self.macro_prefix(), self.options.format.name().to_uppercase())
+	}
+
+	/// How a shader reads the texel at `x`, `y`, e.g. `image[x][y]`
+	pub fn access(&self) -> String {
+		self.encoder().access()
+	}
+
+	/// Short description of the array type and value format
+	pub fn format_description(&self) -> String {
+		self.encoder().format_description()
+	}
+
+	/// Label and value of the minimum API version the output needs
+	pub fn requirement(&self) -> (&'static str, String) {
+		self.encoder().requirement()
+	}
+
+	/// Further labels and values worth reporting, such as the compression achieved
+	pub fn statistics(&self) -> Vec<(&'static str, String)> {
+		self.encoder().statistics()
+	}
+
+	fn encoder(&self) -> Box<dyn Encoder + '_> {
+		encoding::encoder(self.options, &self.pixels, self.order)
+	}
+
+	/// Writes the shader source, calling `progress` with the number of pixels converted since the last call
+	pub fn write(&self, output: &mut impl Write, progress: &dyn Fn(u64)) -> Result<(), ConvertError> {
+		let (outer_len, inner_len) = (self.order.outer_len(), self.order.inner_len());
+		let mut encoder = self.encoder();
+
+		encoder.header(output)?;
+		for outer in 0..outer_len {
+			encoder.row_begin(output, outer)?;
+			for inner in 0..inner_len {
+				let (x, y) = self.order.source(outer, inner);
+				encoder.pixel(output, Position { outer, inner, x, y })?;
+			}
+			encoder.row_end(output, outer)?;
+			progress(inner_len as u64);
+		}
+		encoder.footer(output)?;
+
+		Ok(())
+	}
+}
+
+/// Converts `image` into GLSL source held in memory
+pub fn convert(image: &DynamicImage, options: &ConvertOptions) -> Result<GlslOutput, ConvertError> {
+	let conversion = Conversion::new(image, options)?;
+	let mut source = Vec::new();
+	conversion.write(&mut source, &|_| {})?;
+
+	Ok(GlslOutput {
+		name: options.name.clone(),
+		dimensions: conversion.dimensions(),
+		// Everything written is built from `str`s
+		source: String::from_utf8(source).unwrap()
+	})
+}
+
+/// Converts `image`, streaming the GLSL source into `output`
+pub fn convert_to_writer(image: &DynamicImage, options: &ConvertOptions, output: &mut impl Write) -> Result<(), ConvertError> {
+	Conversion::new(image, options)?.write(output, &|_| {})
+}
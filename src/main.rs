@@ -1,54 +1,486 @@
-#![feature(panic_payload_as_str)]
-
-use std::{fs::OpenOptions, io::Write, path::PathBuf, process::exit, time::Duration};
-use anyhow::Result;
-use clap::Parser;
+use std::{
+	collections::HashSet,
+	env,
+	ffi::OsString,
+	fs::{self, File},
+	io::{BufReader, Write},
+	path::{Path, PathBuf},
+	hash::Hasher,
+	process::exit,
+	thread,
+	time::{Duration, Instant, SystemTime}
+};
+use anyhow::{anyhow, bail, Result};
+use clap::{CommandFactory, Parser};
 use console::Style;
-use image::{GenericImageView, ImageReader};
+use image::{ColorType, DynamicImage, ImageFormat, ImageReader};
+use image_to_glsl_array::{
+	glob,
+	hash::{self, Fnv1a},
+	manifest::{self, Manifest, Table, Value},
+	name,
+	pixels,
+	skybox,
+	transform::{self, Filter, Fit, Region, Size},
+	Animation,
+	Atlas,
+	ColorConversion,
+	ColorSpace,
+	Conversion,
+	ConvertOptions,
+	Encoding,
+	Format,
+	GlslVersion,
+	Layout,
+	PendingOutput,
+	Projection,
+	Transform,
+	WrapMode
+};
 use indicatif::ProgressBar;
 
-/// Converts images to GLSL arrays
+/// How often `--watch` checks the inputs for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Converts images to GLSL arrays, or reads generated arrays back into images
+///
+/// Arguments without a subcommand are those of `convert`, so `image_to_glsl_array <in> <out>`
+/// converts an image
 #[derive(clap::Parser, Debug)]
 #[command(about, long_about)]
+struct Cli {
+	#[command(subcommand)]
+	command: Command
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+	/// Convert images to GLSL, HLSL or WGSL arrays, the default when no subcommand is given
+	Convert(Arguments),
+	/// Pack several images into one array, with a `<name>_rects` table and an index macro per image
+	Atlas(AtlasArguments),
+	/// Read the arrays of a generated file back into images
+	Decode(DecodeArguments),
+	/// Print the format, size, colors and frames of images without converting them
+	Info(InfoArguments),
+	/// Check that every array in generated files is complete and can be read back
+	Validate(ValidateArguments),
+	/// Run every conversion listed in a `glsl-images.toml` manifest
+	Build(BuildArguments)
+}
+
+#[derive(clap::Args, Debug)]
 struct Arguments {
-	/// Image file to convert
-	// #[arg(default_value = "image.png")]
+	/// Image files to convert, wildcards such as `skies/*.png` are expanded, followed by the output
+	/// file to write to, or a directory to write one file per image into. Several inputs and a file
+	/// give a combined file with one array per image
+	#[arg(required = true, num_args = 1.., value_name = "PATHS")]
+	paths: Vec<PathBuf>,
+
+	#[command(flatten)]
+	array: ArrayArguments,
+
+	#[command(flatten)]
+	transform: TransformArguments,
+
+	/// Decode every frame of animated GIF, PNG and WebP inputs, adding a frame duration table
+	/// and `<name>_frame` and `<name>_frame_texel` functions. Still inputs are converted as usual
+	#[arg(long)]
+	animated: bool,
+
+	/// Build a skybox from six cube faces, ordered +X, -X, +Y, -Y, +Z, -Z or by endings such as
+	/// `_px` and `_negz`, or from one equirectangular panorama, reprojected into this projection,
+	/// adding a `<name>_sample` function that takes a direction
+	#[arg(long, value_enum)]
+	skybox: Option<Projection>,
+
+	/// Keep running and convert again whenever the content of an input changes
+	#[arg(long)]
+	watch: bool,
+
+	/// Write the output even if its source hash shows it was made from the same inputs and options
+	#[arg(long)]
+	force: bool,
+
+	/// Set for the `atlas` subcommand, which shares the conversion code
+	#[arg(skip)]
+	atlas: bool
+}
+
+impl Arguments {
+	/// Every path but the last, split off here since clap stops a positional taking several values
+	/// at the first option, so `<in> --helpers <out>` would leave `<out>` unexpected
+	fn inputs(&self) -> &[PathBuf] {
+		&self.paths[..self.paths.len() - 1]
+	}
+
+	fn output(&self) -> &Path {
+		&self.paths[self.paths.len() - 1]
+	}
+}
+
+/// How the generated arrays are written, shared by `convert` and `atlas`
+#[derive(clap::Args, Debug)]
+struct ArrayArguments {
+	/// Identifier of the generated array, also used to prefix helpers and macros [default: input file stem]
+	#[arg(long, value_parser = name::parse)]
+	name: Option<String>,
+
+	/// Order the pixels are stored in [default: column-major for GLSL, row-major for HLSL and WGSL]
+	#[arg(long, value_enum)]
+	layout: Option<Layout>,
+
+	/// Mirror the image horizontally, so x = 0 is the right column
+	#[arg(long)]
+	flip_x: bool,
+
+	/// Mirror the image vertically, so y = 0 is the bottom row like OpenGL texture coordinates
+	#[arg(long)]
+	flip_y: bool,
+
+	/// Color space to write the values in, HDR and EXR images are treated as already linear
+	#[arg(long, value_enum, default_value_t = ColorSpace::Srgb)]
+	color_space: ColorSpace,
+
+	/// Multiply the color channels by alpha
+	#[arg(long)]
+	premultiply_alpha: bool,
+
+	/// Shading language to write
+	#[arg(long, value_enum, default_value_t = Format::Glsl)]
+	format: Format,
+
+	/// Pixel encoding of the generated array
+	#[arg(long, value_enum, default_value_t = Encoding::Vec4)]
+	encoding: Encoding,
+
+	/// Maximum number of palette colors, images with more colors get quantized
+	#[arg(long, default_value_t = 256, value_parser = clap::value_parser!(u16).range(1..=256))]
+	palette_size: u16,
+
+	/// Also generate `<name>_fetch`, `<name>_sample_nearest` and `<name>_sample_bilinear` functions
+	#[arg(long)]
+	helpers: bool,
+
+	/// Wrap mode used by the sampling helpers
+	#[arg(long, value_enum, default_value_t = WrapMode::Clamp)]
+	wrap: WrapMode,
+
+	/// GLSL version to write the output for, older versions get flattened 1D arrays
+	#[arg(long, value_enum, default_value_t = GlslVersion::V430)]
+	glsl_version: GlslVersion,
+
+	/// Write a file meant for `#include`, without `#version` and wrapped in include guards
+	#[arg(long)]
+	include: bool
+}
+
+/// How each image is cropped and resized before it's converted, shared by `convert` and `atlas`.
+/// Frames of animations and faces of skyboxes are each changed on their own
+#[derive(clap::Args, Debug)]
+struct TransformArguments {
+	/// Keep only this region, given as X,Y,WIDTH,HEIGHT in pixels from the top left, before resizing
+	#[arg(long, value_name = "X,Y,WIDTH,HEIGHT")]
+	crop: Option<Region>,
+
+	/// Resize to WIDTHxHEIGHT such as 64x64, arrays much past 128 x 128 are slow for drivers to compile
+	#[arg(long, value_name = "WIDTHxHEIGHT")]
+	resize: Option<Size>,
+
+	/// How images with another aspect ratio fit the --resize size
+	#[arg(long, value_enum, default_value_t = Fit::Contain)]
+	fit: Fit,
+
+	/// Multiply the size by this factor after any crop and resize, such as 0.5 for half the size
+	#[arg(long, value_parser = transform::parse_scale)]
+	scale: Option<f32>,
+
+	/// Filter used by --resize and --scale, nearest keeps pixel art sharp
+	#[arg(long, value_enum, default_value_t = Filter::Lanczos3)]
+	filter: Filter
+}
+
+impl TransformArguments {
+	fn transform(&self) -> Transform {
+		Transform {
+			crop: self.crop,
+			resize: self.resize,
+			fit: self.fit,
+			scale: self.scale,
+			filter: self.filter
+		}
+	}
+}
+
+#[derive(clap::Args, Debug)]
+struct AtlasArguments {
+	/// Image files to pack, wildcards such as `sprites/*.png` are expanded, followed by the output
+	/// file to write the atlas to
+	#[arg(required = true, num_args = 1.., value_name = "PATHS")]
+	paths: Vec<PathBuf>,
+
+	#[command(flatten)]
+	array: ArrayArguments,
+
+	#[command(flatten)]
+	transform: TransformArguments,
+
+	/// Keep running and pack again whenever the content of an input changes
+	#[arg(long)]
+	watch: bool,
+
+	/// Write the atlas even if its source hash shows it was made from the same inputs and options
+	#[arg(long)]
+	force: bool
+}
+
+impl From<AtlasArguments> for Arguments {
+	fn from(atlas: AtlasArguments) -> Self {
+		Arguments {
+			paths: atlas.paths,
+			array: atlas.array,
+			transform: atlas.transform,
+			animated: false,
+			skybox: None,
+			watch: atlas.watch,
+			force: atlas.force,
+			atlas: true
+		}
+	}
+}
+
+#[derive(clap::Args, Debug)]
+struct DecodeArguments {
+	/// GLSL, HLSL or WGSL file written by this tool
 	input: PathBuf,
 
-	/// Output file to write to
-	// #[arg(default_value = "image.glsl")]
-	output: PathBuf
+	/// Image file to write, or a directory to write one `<name>.png` per array into.
+	/// Files holding several arrays need a directory
+	output: PathBuf,
+
+	/// Color space the values were written in, linear values are encoded to sRGB again
+	#[arg(long, value_enum, default_value_t = ColorSpace::Srgb)]
+	color_space: ColorSpace,
+
+	/// The color channels were multiplied by alpha, divide them by it again
+	#[arg(long)]
+	premultiply_alpha: bool
+}
+
+#[derive(clap::Args, Debug)]
+struct InfoArguments {
+	/// Image files to describe, wildcards are expanded
+	#[arg(required = true)]
+	inputs: Vec<PathBuf>
+}
+
+#[derive(clap::Args, Debug)]
+struct ValidateArguments {
+	/// Generated GLSL, HLSL or WGSL files to check, wildcards are expanded
+	#[arg(required = true)]
+	files: Vec<PathBuf>
+}
+
+#[derive(clap::Args, Debug)]
+struct BuildArguments {
+	/// Manifest with an `[[image]]` table per conversion, holding `input` or `inputs`, `output`
+	/// and the options of `convert` without their dashes. Keys above the tables apply to every
+	/// image, `atlas = true` packs the inputs like `atlas` and paths are relative to the manifest
+	#[arg(default_value = manifest::FILE_NAME)]
+	manifest: PathBuf,
+
+	/// Write every output even if it was made from the same inputs and options
+	#[arg(long)]
+	force: bool
+}
+
+/// Settings shared by every image, the array name being per image
+fn convert_options(arguments: &Arguments, name: &str) -> ConvertOptions {
+	let options = ConvertOptions::new()
+		.name(name)
+		.format(arguments.array.format)
+		.encoding(arguments.array.encoding)
+		.palette_size(arguments.array.palette_size)
+		.flip_x(arguments.array.flip_x)
+		.flip_y(arguments.array.flip_y)
+		.color_space(arguments.array.color_space)
+		.premultiply_alpha(arguments.array.premultiply_alpha)
+		.helpers(arguments.array.helpers)
+		.wrap(arguments.array.wrap)
+		.glsl_version(arguments.array.glsl_version)
+		.include(arguments.array.include)
+	;
+	match arguments.array.layout {
+		Some(layout) => options.layout(layout),
+		None => options
+	}
+}
+
+/// Decodes the image `reader` points at, with `--animated` every frame of it stacked top to
+/// bottom along with the frame durations
+fn decode(arguments: &Arguments, reader: ImageReader<BufReader<File>>) -> Result<(DynamicImage, Vec<u32>)> {
+	match arguments.animated {
+		false => Ok((reader.decode()?, Vec::new())),
+		true => {
+			let animation = Animation::decode(reader)?;
+			Ok((animation.image, animation.frame_durations))
+		}
+	}
+}
+
+fn input_name(input: &Path) -> String {
+	name::sanitize(&input.file_stem().unwrap_or_default().to_string_lossy())
+}
+
+/// File written for `name` inside the output directory, e.g. `sky.glsl`
+fn output_path(directory: &Path, name: &str, format: Format) -> PathBuf {
+	directory.join(format!("{}.{}", name, format.name()))
+}
+
+/// Source hash of converting `inputs` into an array named `name`, also covering the options
+/// the library doesn't take
+fn source_hash(arguments: &Arguments, name: &str, inputs: &[impl AsRef<Path>]) -> Result<u64> {
+	let settings = (arguments.transform.transform(), arguments.animated, arguments.atlas);
+	Ok(convert_options(arguments, name).skybox(arguments.skybox).source_hash_of(inputs, settings)?)
+}
+
+/// Whether `output` records `hash` as its source hash and `--force` isn't given, so writing it again can be skipped
+fn up_to_date(arguments: &Arguments, output: &Path, hash: u64) -> bool {
+	!arguments.force && hash::is_up_to_date(output, hash)
+}
+
+/// Files matching each of `patterns`, reporting patterns that match nothing
+fn expand_inputs(patterns: &[PathBuf]) -> Result<Vec<PathBuf>> {
+	let mut inputs = Vec::new();
+	for pattern in patterns {
+		let matched = glob::expand(pattern)?;
+		if matched.is_empty() {
+			bail!("No files match {}", pattern.display());
+		}
+		inputs.extend(matched);
+	}
+	Ok(inputs)
+}
+
+/// Whether `output` names a directory to write into, either an existing one or by ending with a separator
+fn is_directory(output: &Path) -> bool {
+	output.is_dir() || output.to_string_lossy().ends_with(['/', std::path::MAIN_SEPARATOR])
+}
+
+/// Converts `inputs` as an atlas, a skybox, a single image or a batch, whichever `arguments` ask for,
+/// telling whether anything was written rather than found up to date
+fn convert_inputs(arguments: &Arguments, inputs: &[PathBuf], to_directory: bool, verbose: bool) -> Result<bool> {
+	match (arguments.skybox, arguments.atlas, inputs.len()) {
+		(Some(projection), _, _) => convert_skybox(arguments, inputs, projection, to_directory, verbose),
+		(None, true, _) => convert_atlas(arguments, inputs, to_directory, verbose),
+		(None, false, 1) => convert_single(arguments, &inputs[0], to_directory, verbose),
+		(None, false, _) => convert_batch(arguments, inputs, to_directory, verbose)
+	}
 }
 
 fn run(arguments: Arguments) -> Result<()> {
-	let style_heading = Style::new().underlined();
+	if arguments.paths.len() < 2 {
+		bail!("The output is missing, give it after the inputs such as `sky.png sky.glsl`");
+	}
+	let inputs = expand_inputs(arguments.inputs())?;
+
+	let to_directory = is_directory(arguments.output());
+	if to_directory {
+		fs::create_dir_all(arguments.output())?;
+	}
+
+	convert_inputs(&arguments, &inputs, to_directory, true)?;
+
+	if arguments.watch {
+		watch(&arguments, &inputs, to_directory);
+	}
+	Ok(())
+}
+
+/// Modification time and size, checked before reading a whole file to compare its content
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+	let metadata = fs::metadata(path).ok()?;
+	Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn content_hash(path: &Path) -> Option<u64> {
+	let mut hasher = Fnv1a::default();
+	hasher.write(&fs::read(path).ok()?);
+	Some(hasher.finish())
+}
+
+/// Polls `inputs` and converts them again whenever their content changes, until interrupted
+fn watch(arguments: &Arguments, inputs: &[PathBuf], to_directory: bool) -> ! {
 	let style_key = Style::new().bold();
-	let style_value = Style::new().bold().cyan();
+	let style_success = Style::new().bold().green();
+	let style_error = Style::new().bold().red();
 
-	let mut progress = ProgressBar::new_spinner();
-	progress.set_message("Decoding image...");
-	progress.enable_steady_tick(Duration::from_millis(200));
+	let mut stamps: Vec<_> = inputs.iter().map(|input| file_stamp(input)).collect();
+	let mut hashes: Vec<_> = inputs.iter().map(|input| content_hash(input)).collect();
 
-	let reader = ImageReader::open(&arguments.input)?.with_guessed_format()?;
-	let format = reader.format();
-	let image = reader.decode()?;
-	let dimensions = image.dimensions();
-	progress.finish_and_clear();
+	println!(
+		"\nWatching {} for changes, press Ctrl+C to stop",
+		match inputs.len() {
+			1 => inputs[0].display().to_string(),
+			count => format!("{} images", count)
+		}
+	);
+	loop {
+		thread::sleep(POLL_INTERVAL);
 
-	let mut output = String::new();
-	let mut output_file = OpenOptions::new()
-		.create(true)
-		.write(true)
-		.read(false)
-		.truncate(true)
-		.open(&arguments.output)?
-	;
+		let mut changed = Vec::new();
+		for (index, input) in inputs.iter().enumerate() {
+			let stamp = file_stamp(input);
+			if stamp == stamps[index] {
+				continue;
+			}
+			stamps[index] = stamp;
+			// Saving without edits or touching the file only updates the modification time
+			let hash = content_hash(input);
+			if hash != hashes[index] {
+				hashes[index] = hash;
+				changed.push(input.clone());
+			}
+		}
+		if changed.is_empty() {
+			continue;
+		}
+
+		let started = Instant::now();
+		let result = match (arguments.skybox, arguments.atlas, inputs.len(), to_directory) {
+			// Files in a directory are independent, anything else is written as a whole
+			(None, false, 2.., true) => convert_batch(arguments, &changed, true, false),
+			_ => convert_inputs(arguments, inputs, to_directory, false)
+		};
+		let changed = changed.iter().map(|input| input.display().to_string()).collect::<Vec<_>>().join(", ");
+		match result {
+			Ok(_) => println!(
+				"{} {} in {} ms",
+				style_success.apply_to("Converted"),
+				style_key.apply_to(changed),
+				started.elapsed().as_millis()
+			),
+			// A paint program may still be writing the file, the next save gets picked up again
+			Err(error) => println!(
+				"{} {}: {}",
+				style_error.apply_to("Failed"),
+				style_key.apply_to(changed),
+				error
+			)
+		}
+	}
+}
+
+fn print_input_summary(input: &Path, dimensions: (u32, u32), format: Option<ImageFormat>, color: ColorType) {
+	let style_heading = Style::new().underlined();
+	let style_key = Style::new().bold();
+	let style_value = Style::new().bold().cyan();
 
 	println!("{}:", style_heading.apply_to("Input file"));
 	println!(
 		"  - {}: {}",
 		style_key.apply_to("Path"),
-		style_value.apply_to(&arguments.input.to_str().unwrap())
+		style_value.apply_to(input.display())
 	);
 	println!(
 		"  - {}: {}",
@@ -60,84 +492,991 @@ fn run(arguments: Arguments) -> Result<()> {
 			}
 		)
 	);
+	println!(
+		"  - {}: {}",
+		style_key.apply_to("Color type"),
+		style_value.apply_to(pixels::describe_color(color))
+	);
 	println!(
 		"  - {}: {} x {}",
 		style_key.apply_to("Dimensions"),
 		style_value.apply_to(dimensions.0),
 		style_value.apply_to(dimensions.1)
 	);
-	print!("\n");
+}
+
+fn print_output_summary(arguments: &Arguments, output_path: &Path, conversion: &Conversion) {
+	let style_heading = Style::new().underlined();
+	let style_key = Style::new().bold();
+	let style_value = Style::new().bold().cyan();
+	let name = conversion.name();
+
 	println!("{}:", style_heading.apply_to("Output file"));
 	println!(
 		"  - {}: {}",
 		style_key.apply_to("Path"),
-		style_value.apply_to(&arguments.output.to_str().unwrap())
+		style_value.apply_to(output_path.display())
+	);
+	println!(
+		"  - {}: {}",
+		style_key.apply_to("Name"),
+		style_value.apply_to(name)
 	);
 	println!(
 		"  - {}: {}",
 		style_key.apply_to("Format"),
-		style_value.apply_to("vec4[][], RGBA, 0..1 value range")
+		style_value.apply_to(conversion.format_description())
+	);
+	println!(
+		"  - {}: {}",
+		style_key.apply_to("Layout"),
+		style_value.apply_to(format!(
+			"{}, origin at the {} {}",
+			conversion.access(),
+			match arguments.array.flip_y {
+				false => "top",
+				true => "bottom"
+			},
+			match arguments.array.flip_x {
+				false => "left",
+				true => "right"
+			}
+		))
+	);
+	if let Some(description) = arguments.transform.transform().describe() {
+		println!(
+			"  - {}: {}",
+			style_key.apply_to("Transform"),
+			style_value.apply_to(format!(
+				"{}, giving {} x {} texels",
+				description,
+				conversion.dimensions().0,
+				conversion.dimensions().1
+			))
+		);
+	}
+	let requirement = conversion.requirement();
+	println!(
+		"  - {}: {}",
+		style_key.apply_to(requirement.0),
+		style_value.apply_to(requirement.1)
+	);
+	for (key, value) in conversion.statistics() {
+		println!(
+			"  - {}: {}",
+			style_key.apply_to(key),
+			style_value.apply_to(value)
+		);
+	}
+	if arguments.array.include {
+		println!(
+			"  - {}: {}",
+			style_key.apply_to("Include guard"),
+			style_value.apply_to(conversion.include_guard())
+		);
+	}
+	if arguments.array.helpers {
+		println!(
+			"  - {}: {}",
+			style_key.apply_to("Sampling helpers"),
+			style_value.apply_to(format!(
+				"{0}_fetch, {0}_sample_nearest, {0}_sample_bilinear ({1} wrap)",
+				name,
+				arguments.array.wrap.name()
+			))
+		);
+	}
+	let frame_durations = conversion.frame_durations();
+	if !frame_durations.is_empty() {
+		println!(
+			"  - {}: {}",
+			style_key.apply_to("Animation"),
+			style_value.apply_to(format!(
+				"{0} frames of {1} x {2}, {3} ms loop, {4}_frame and {4}_frame_texel",
+				frame_durations.len(),
+				conversion.dimensions().0,
+				conversion.dimensions().1 / (frame_durations.len() as u32),
+				frame_durations.iter().map(|duration| *duration as u64).sum::<u64>(),
+				name
+			))
+		);
+	}
+	if let Some(projection) = conversion.skybox() {
+		let dimensions = conversion.dimensions();
+		println!(
+			"  - {}: {}",
+			style_key.apply_to("Skybox"),
+			style_value.apply_to(format!(
+				"{}, {}, {}_sample",
+				projection.name(),
+				match projection {
+					Projection::Cubemap => format!("6 faces of {0} x {0}", dimensions.0),
+					Projection::Equirectangular => format!("{} x {} panorama", dimensions.0, dimensions.1)
+				},
+				name
+			))
+		);
+	}
+}
+
+/// Tells that `output` was made from the same inputs and options and is left as it is
+fn print_up_to_date(output: &Path) {
+	let style_heading = Style::new().underlined();
+	let style_key = Style::new().bold();
+	let style_value = Style::new().bold().cyan();
+
+	println!("{}:", style_heading.apply_to("Output"));
+	println!(
+		"  - {}: {}",
+		style_key.apply_to("Path"),
+		style_value.apply_to(output.display())
 	);
 	println!(
 		"  - {}: {}",
-		style_key.apply_to("Minimum OpenGL version: "),
-		style_value.apply_to("Core 4.2")
+		style_key.apply_to("Up to date"),
+		style_value.apply_to("same inputs and options, --force writes it again")
 	);
+}
+
+/// Converts one image, printing the full summary and progress when `verbose`
+fn convert_single(arguments: &Arguments, input: &Path, to_directory: bool, verbose: bool) -> Result<bool> {
+	let name = match arguments.array.name {
+		Some(ref value) => value.clone(),
+		None => input_name(input)
+	};
+	let output_path = match to_directory {
+		false => arguments.output().to_path_buf(),
+		true => output_path(arguments.output(), &name, arguments.array.format)
+	};
+	let hash = source_hash(arguments, &name, &[input])?;
+	if up_to_date(arguments, &output_path, hash) {
+		if verbose {
+			print_up_to_date(&output_path);
+		}
+		return Ok(false);
+	}
+
+	let mut progress = match verbose {
+		false => ProgressBar::hidden(),
+		true => ProgressBar::new_spinner()
+	};
+	progress.set_message("Decoding image...");
+	progress.enable_steady_tick(Duration::from_millis(200));
+
+	let reader = ImageReader::open(input)?.with_guessed_format()?;
+	let format = reader.format();
+	let (image, frame_durations) = decode(arguments, reader)?;
+	let (color, source_dimensions) = (image.color(), (image.width(), image.height()));
+	let image = arguments.transform.transform().apply_frames(image, frame_durations.len())?;
+	progress.finish_and_clear();
+
+	let options = convert_options(arguments, &name)
+		.frame_durations(frame_durations)
+		.source_hash(Some(hash))
+	;
+	let conversion = Conversion::new(&image, &options)?;
+	let dimensions = conversion.dimensions();
+	drop(image);
+
+	let mut output = PendingOutput::create(&output_path)?;
+
+	if verbose {
+		print_input_summary(input, source_dimensions, format, color);
+		println!();
+		print_output_summary(arguments, &output_path, &conversion);
+	}
 
 	let total_pixels = (dimensions.0 as u64) * (dimensions.1 as u64);
-	let mut processed_pixels: u64 = 0;
-	progress = ProgressBar::new(total_pixels);
+	progress = match verbose {
+		false => ProgressBar::hidden(),
+		true => ProgressBar::new(total_pixels)
+	};
 	progress.set_message("Converting image...");
 
-	output += "#version 420\n";
-	output += &format!("vec4 image[{}][{}] = {{\n", dimensions.0, dimensions.1)[..];
-	for x in 0..dimensions.0 {
-		output += "\t{";
-		for y in 0..dimensions.1 {
-			let pixel = image.get_pixel(x, y).0;
-			output += &format!(
-				"vec4({:.7}, {:.7}, {:.7}, {:.7})",
-				(1 as f64) / (255 as f64) * (pixel[0] as f64),
-				(1 as f64) / (255 as f64) * (pixel[1] as f64),
-				(1 as f64) / (255 as f64) * (pixel[2] as f64),
-				(1 as f64) / (255 as f64) * (pixel[3] as f64)
-			)[..];
-			output += match (y + 1) == dimensions.1 {
-				false => ", ",
-				true => "}"
-			};
-			processed_pixels += 1;
-			progress.set_position(processed_pixels);
-		}
-		output += match (x + 1) == dimensions.0 {
-			false => ",\n",
-			true => "\n};"
+	conversion.write(&mut output, &|pixels| progress.inc(pixels))?;
+	output.finish()?;
+
+	progress.finish_and_clear();
+
+	Ok(true)
+}
+
+/// Converts several images into a directory or a combined file, listing each image and
+/// showing the shared progress when `verbose`
+fn convert_batch(arguments: &Arguments, inputs: &[PathBuf], to_directory: bool, verbose: bool) -> Result<bool> {
+	let style_heading = Style::new().underlined();
+	let style_key = Style::new().bold();
+	let style_value = Style::new().bold().cyan();
+
+	if arguments.array.name.is_some() {
+		bail!("--name only works with a single input, arrays are named after their files otherwise");
+	}
+	let names: Vec<String> = inputs.iter().map(|input| input_name(input)).collect();
+	for (index, name) in names.iter().enumerate() {
+		if let Some(other) = names[..index].iter().position(|other| other == name) {
+			bail!(
+				"{} and {} would both be named {}",
+				inputs[other].display(),
+				inputs[index].display(),
+				name
+			);
+		}
+	}
+
+	// A combined file is written as a whole, files in a directory only for the images that changed
+	let hashes = match to_directory {
+		false => vec![source_hash(arguments, &names[0], inputs)?; inputs.len()],
+		true => inputs
+			.iter()
+			.zip(&names)
+			.map(|(input, name)| source_hash(arguments, name, &[input]))
+			.collect::<Result<Vec<_>>>()?
+	};
+	let stale = match to_directory {
+		false => vec![!up_to_date(arguments, arguments.output(), hashes[0]); inputs.len()],
+		true => names
+			.iter()
+			.zip(&hashes)
+			.map(|(name, hash)| !up_to_date(arguments, &output_path(arguments.output(), name, arguments.array.format), *hash))
+			.collect()
+	};
+	if !stale.contains(&true) {
+		if verbose {
+			print_up_to_date(arguments.output());
+		}
+		return Ok(false);
+	}
+
+	// Only the headers are read here, so the progress can cover every image from the start
+	let transform = arguments.transform.transform();
+	let mut header_pixels = Vec::with_capacity(inputs.len());
+	for (input, stale) in inputs.iter().zip(&stale) {
+		if !stale {
+			header_pixels.push(0);
+			continue;
+		}
+		let dimensions = ImageReader::open(input)?.with_guessed_format()?.into_dimensions()?;
+		let dimensions = transform.dimensions(dimensions).map_err(|error| anyhow!("{}, {}", input.display(), error))?;
+		header_pixels.push((dimensions.0 as u64) * (dimensions.1 as u64));
+	}
+	let total_pixels = header_pixels.iter().sum();
+
+	if verbose {
+		println!(
+			"{}:",
+			style_heading.apply_to(match to_directory {
+				false => "Output file",
+				true => "Output directory"
+			})
+		);
+		println!(
+			"  - {}: {}",
+			style_key.apply_to("Path"),
+			style_value.apply_to(arguments.output().display())
+		);
+		println!(
+			"  - {}: {}",
+			style_key.apply_to("Images"),
+			style_value.apply_to(inputs.len())
+		);
+		println!();
+		println!("{}:", style_heading.apply_to("Input files"));
+	}
+
+	let progress = match verbose {
+		false => ProgressBar::hidden(),
+		true => ProgressBar::new(total_pixels)
+	};
+	let mut combined = match to_directory {
+		false => Some(PendingOutput::create(arguments.output())?),
+		true => None
+	};
+
+	for (index, (input, name)) in inputs.iter().zip(&names).enumerate() {
+		if !stale[index] {
+			if verbose {
+				progress.suspend(|| {
+					println!(
+						"  - {}: {}, {}",
+						style_key.apply_to(input.display()),
+						style_value.apply_to(name),
+						style_value.apply_to("up to date")
+					)
+				});
+			}
+			continue;
+		}
+		progress.set_message(format!("Converting {}...", input.display()));
+
+		let (image, frame_durations) = decode(arguments, ImageReader::open(input)?.with_guessed_format()?)?;
+		let image = transform.apply_frames(image, frame_durations.len())?;
+		// Only the first array of a combined file gets the `#version` line and source hash
+		let first = index == 0 || to_directory;
+		let options = convert_options(arguments, name)
+			.preamble(first)
+			.frame_durations(frame_durations)
+			.source_hash(first.then_some(hashes[index]))
+		;
+		let conversion = Conversion::new(&image, &options)?;
+		drop(image);
+
+		// The header only tells the size of the first frame of an animation
+		let dimensions = conversion.dimensions();
+		progress.inc_length(((dimensions.0 as u64) * (dimensions.1 as u64)).saturating_sub(header_pixels[index]));
+
+		match combined {
+			Some(ref mut output) => {
+				if index != 0 {
+					writeln!(output)?;
+				}
+				conversion.write(output, &|pixels| progress.inc(pixels))?;
+			},
+			None => {
+				let mut output = PendingOutput::create(&output_path(arguments.output(), name, arguments.array.format))?;
+				conversion.write(&mut output, &|pixels| progress.inc(pixels))?;
+				output.finish()?;
+			}
+		}
+
+		if !verbose {
+			continue;
+		}
+		progress.suspend(|| {
+			println!(
+				"  - {}: {}, {} x {}, {}",
+				style_key.apply_to(input.display()),
+				style_value.apply_to(name),
+				style_value.apply_to(dimensions.0),
+				style_value.apply_to(dimensions.1),
+				style_value.apply_to(conversion.format_description())
+			)
+		});
+	}
+	if let Some(output) = combined {
+		output.finish()?;
+	}
+
+	progress.finish_and_clear();
+
+	Ok(true)
+}
+
+/// Packs all images into one atlas array named after the output file, printing the sprite
+/// positions and summary when `verbose`
+fn convert_atlas(arguments: &Arguments, inputs: &[PathBuf], to_directory: bool, verbose: bool) -> Result<bool> {
+	let style_heading = Style::new().underlined();
+	let style_key = Style::new().bold();
+	let style_value = Style::new().bold().cyan();
+
+	if to_directory {
+		bail!("An atlas is written to a single file, the output can't be a directory");
+	}
+	let name = match arguments.array.name {
+		Some(ref value) => value.clone(),
+		None => input_name(arguments.output())
+	};
+	let hash = source_hash(arguments, &name, inputs)?;
+	if up_to_date(arguments, arguments.output(), hash) {
+		if verbose {
+			print_up_to_date(arguments.output());
+		}
+		return Ok(false);
+	}
+
+	let mut progress = match verbose {
+		false => ProgressBar::hidden(),
+		true => ProgressBar::new_spinner()
+	};
+	progress.set_message("Decoding images...");
+	progress.enable_steady_tick(Duration::from_millis(200));
+
+	let transform = arguments.transform.transform();
+	let mut images = Vec::with_capacity(inputs.len());
+	for input in inputs {
+		let image = ImageReader::open(input)?.with_guessed_format()?.decode()?;
+		images.push((input_name(input), transform.apply(image).map_err(|error| anyhow!("{}, {}", input.display(), error))?));
+	}
+	let atlas = Atlas::pack(&images);
+	drop(images);
+	progress.finish_and_clear();
+
+	let options = convert_options(arguments, &name)
+		.sprites(atlas.sprites.clone())
+		.source_hash(Some(hash))
+	;
+	let conversion = Conversion::new(&atlas.image, &options)?;
+	let dimensions = conversion.dimensions();
+
+	let mut output = PendingOutput::create(arguments.output())?;
+
+	if verbose {
+		println!("{}:", style_heading.apply_to("Input files"));
+		for (input, sprite) in inputs.iter().zip(&atlas.sprites) {
+			println!(
+				"  - {}: {}, {} x {} at {}, {}",
+				style_key.apply_to(input.display()),
+				style_value.apply_to(&sprite.name),
+				style_value.apply_to(sprite.width),
+				style_value.apply_to(sprite.height),
+				style_value.apply_to(sprite.x),
+				style_value.apply_to(sprite.y)
+			);
 		}
+		println!();
+		print_output_summary(arguments, arguments.output(), &conversion);
+		println!(
+			"  - {}: {}_rects, {} x {} atlas",
+			style_key.apply_to("Sprite table"),
+			style_value.apply_to(&name),
+			style_value.apply_to(dimensions.0),
+			style_value.apply_to(dimensions.1)
+		);
 	}
+	drop(atlas);
+
+	let total_pixels = (dimensions.0 as u64) * (dimensions.1 as u64);
+	progress = match verbose {
+		false => ProgressBar::hidden(),
+		true => ProgressBar::new(total_pixels)
+	};
+	progress.set_message("Converting atlas...");
+
+	conversion.write(&mut output, &|pixels| progress.inc(pixels))?;
+	output.finish()?;
 
 	progress.finish_and_clear();
-	progress = ProgressBar::new_spinner();
 
-	progress.set_message("Writing output file...");
+	Ok(true)
+}
+
+/// Builds a skybox array named after the output file from six faces, or after the input
+/// from one panorama, printing the inputs and summary when `verbose`
+fn convert_skybox(
+	arguments: &Arguments,
+	inputs: &[PathBuf],
+	projection: Projection,
+	to_directory: bool,
+	verbose: bool
+) -> Result<bool> {
+	let style_heading = Style::new().underlined();
+	let style_key = Style::new().bold();
+	let style_value = Style::new().bold().cyan();
+
+	if to_directory {
+		bail!("--skybox writes a single file, the output can't be a directory");
+	}
+	if arguments.animated {
+		bail!("--skybox can't be combined with --animated");
+	}
+	let source = match inputs.len() {
+		1 => Projection::Equirectangular,
+		6 => Projection::Cubemap,
+		count => bail!("--skybox takes six cube faces or one panorama, not {} images", count)
+	};
+	// Faces named after their direction can be given in any order, e.g. from a wildcard
+	let face = |input: &PathBuf| skybox::face_index(&input.file_stem().unwrap_or_default().to_string_lossy());
+	let mut inputs = inputs.to_vec();
+	if source == Projection::Cubemap && (0..6).all(|index| inputs.iter().any(|input| face(input) == Some(index))) {
+		inputs.sort_by_key(face);
+	}
+	let name = match (&arguments.array.name, source) {
+		(Some(value), _) => value.clone(),
+		(None, Projection::Equirectangular) => input_name(&inputs[0]),
+		(None, Projection::Cubemap) => input_name(arguments.output())
+	};
+	// Hashed after sorting, since the faces are stacked in this order
+	let hash = source_hash(arguments, &name, &inputs)?;
+	if up_to_date(arguments, arguments.output(), hash) {
+		if verbose {
+			print_up_to_date(arguments.output());
+		}
+		return Ok(false);
+	}
+
+	let mut progress = match verbose {
+		false => ProgressBar::hidden(),
+		true => ProgressBar::new_spinner()
+	};
+	progress.set_message("Decoding images...");
 	progress.enable_steady_tick(Duration::from_millis(200));
 
-	output_file.write_all(output.as_bytes())?;
+	let transform = arguments.transform.transform();
+	let (mut images, mut sizes) = (Vec::with_capacity(inputs.len()), Vec::with_capacity(inputs.len()));
+	for input in &inputs {
+		let image = ImageReader::open(input)?.with_guessed_format()?.decode()?;
+		sizes.push((image.width(), image.height()));
+		images.push(transform.apply(image).map_err(|error| anyhow!("{}, {}", input.display(), error))?);
+	}
+	let image = match source {
+		Projection::Cubemap => skybox::stack_faces(&images)?,
+		Projection::Equirectangular => images.pop().unwrap()
+	};
+	drop(images);
+	if source != projection {
+		progress.set_message("Reprojecting skybox...");
+	}
+	let image = skybox::reproject(&image, source, projection);
+	progress.finish_and_clear();
+
+	let options = convert_options(arguments, &name)
+		.skybox(Some(projection))
+		.source_hash(Some(hash))
+	;
+	let conversion = Conversion::new(&image, &options)?;
+	let dimensions = conversion.dimensions();
+	drop(image);
+
+	let mut output = PendingOutput::create(arguments.output())?;
+
+	if verbose {
+		println!("{}:", style_heading.apply_to("Input files"));
+		for (index, (input, size)) in inputs.iter().zip(&sizes).enumerate() {
+			println!(
+				"  - {}: {}, {} x {}",
+				style_key.apply_to(input.display()),
+				style_value.apply_to(match source {
+					Projection::Cubemap => ["+X", "-X", "+Y", "-Y", "+Z", "-Z"][index],
+					Projection::Equirectangular => "panorama"
+				}),
+				style_value.apply_to(size.0),
+				style_value.apply_to(size.1)
+			);
+		}
+		println!();
+		print_output_summary(arguments, arguments.output(), &conversion);
+	}
+
+	let total_pixels = (dimensions.0 as u64) * (dimensions.1 as u64);
+	progress = match verbose {
+		false => ProgressBar::hidden(),
+		true => ProgressBar::new(total_pixels)
+	};
+	progress.set_message("Converting skybox...");
+
+	conversion.write(&mut output, &|pixels| progress.inc(pixels))?;
+	output.finish()?;
 
 	progress.finish_and_clear();
 
+	Ok(true)
+}
+
+/// Writes the arrays of a generated file back out as images
+fn decode_arrays(arguments: &DecodeArguments) -> Result<()> {
+	let style_heading = Style::new().underlined();
+	let style_key = Style::new().bold();
+	let style_value = Style::new().bold().cyan();
+
+	let source = fs::read_to_string(&arguments.input)?;
+	let images = image_to_glsl_array::decode(
+		&source,
+		ColorConversion {
+			color_space: arguments.color_space,
+			premultiply_alpha: arguments.premultiply_alpha
+		}
+	)?;
+
+	let to_directory = is_directory(&arguments.output);
+	if images.len() > 1 && !to_directory {
+		bail!(
+			"{} holds {} arrays, the output has to be a directory",
+			arguments.input.display(),
+			images.len()
+		);
+	}
+	if to_directory {
+		fs::create_dir_all(&arguments.output)?;
+	}
+
+	println!("{}:", style_heading.apply_to("Input file"));
+	println!(
+		"  - {}: {}",
+		style_key.apply_to("Path"),
+		style_value.apply_to(arguments.input.display())
+	);
+	println!(
+		"  - {}: {}",
+		style_key.apply_to("Arrays"),
+		style_value.apply_to(images.len())
+	);
+	println!();
+	println!("{}:", style_heading.apply_to("Output files"));
+
+	for (name, image) in images {
+		let output_path = match to_directory {
+			false => arguments.output.clone(),
+			true => arguments.output.join(format!("{}.png", name))
+		};
+		// Only OpenEXR keeps floating point RGBA, other formats get 16 bits per channel
+		let image = match (image.color(), ImageFormat::from_path(&output_path)) {
+			(ColorType::Rgba32F, Ok(ImageFormat::OpenExr)) => image,
+			(ColorType::Rgba32F, _) => DynamicImage::ImageRgba16(image.to_rgba16()),
+			_ => image
+		};
+		image.save(&output_path)?;
+
+		println!(
+			"  - {}: {}, {} x {}, {}",
+			style_key.apply_to(output_path.display()),
+			style_value.apply_to(name),
+			style_value.apply_to(image.width()),
+			style_value.apply_to(image.height()),
+			style_value.apply_to(pixels::describe_color(image.color()))
+		);
+	}
+
+	Ok(())
+}
+
+/// Prints the format, size and distinct colors of images, and the frames of animations
+fn describe_images(arguments: &InfoArguments) -> Result<()> {
+	let style_key = Style::new().bold();
+	let style_value = Style::new().bold().cyan();
+
+	for (index, input) in expand_inputs(&arguments.inputs)?.iter().enumerate() {
+		let reader = ImageReader::open(input)?.with_guessed_format()?;
+		let format = reader.format();
+		let image = reader.decode()?;
+
+		if index != 0 {
+			println!();
+		}
+		print_input_summary(input, (image.width(), image.height()), format, image.color());
+		let animation = Animation::decode(ImageReader::open(input)?.with_guessed_format()?)?;
+		if animation.is_animated() {
+			let durations = &animation.frame_durations;
+			println!(
+				"  - {}: {}",
+				style_key.apply_to("Frames"),
+				style_value.apply_to(format!(
+					"{}, {} ms loop",
+					durations.len(),
+					durations.iter().map(|duration| *duration as u64).sum::<u64>()
+				))
+			);
+		}
+		// Palettes are built from the 8-bit colors
+		let colors = image.to_rgba8().pixels().map(|pixel| pixel.0).collect::<HashSet<_>>().len();
+		println!(
+			"  - {}: {}",
+			style_key.apply_to("Distinct colors"),
+			style_value.apply_to(match colors <= 256 {
+				false => colors.to_string(),
+				true => format!("{}, a palette holds them without quantizing", colors)
+			})
+		);
+	}
+	Ok(())
+}
+
+/// Reads every array of each file back, failing if any of them can't be
+fn validate_files(arguments: &ValidateArguments) -> Result<()> {
+	let style_heading = Style::new().underlined();
+	let style_key = Style::new().bold();
+	let style_value = Style::new().bold().cyan();
+	let style_success = Style::new().bold().green();
+	let style_error = Style::new().bold().red();
+
+	let files = expand_inputs(&arguments.files)?;
+	let conversion = ColorConversion {
+		color_space: ColorSpace::Srgb,
+		premultiply_alpha: false
+	};
+
+	println!("{}:", style_heading.apply_to("Files"));
+	let mut invalid = 0;
+	for file in &files {
+		let arrays = fs::read_to_string(file)
+			.map_err(anyhow::Error::from)
+			.and_then(|source| Ok(image_to_glsl_array::decode(&source, conversion)?))
+		;
+		match arrays {
+			Ok(arrays) => println!(
+				"  - {}: {}, {}",
+				style_key.apply_to(file.display()),
+				style_success.apply_to("valid"),
+				style_value.apply_to(
+					arrays
+						.iter()
+						.map(|(name, image)| format!("{} {} x {}", name, image.width(), image.height()))
+						.collect::<Vec<_>>()
+						.join(", ")
+				)
+			),
+			Err(error) => {
+				invalid += 1;
+				println!(
+					"  - {}: {}, {}",
+					style_key.apply_to(file.display()),
+					style_error.apply_to("invalid"),
+					error
+				);
+			}
+		}
+	}
+
+	if invalid != 0 {
+		bail!("{} of {} files are invalid", invalid, files.len());
+	}
+	Ok(())
+}
+
+/// Command line doing the conversion of a manifest `image`, with its paths resolved from `directory`
+fn manifest_command_line(defaults: &Table, image: &Table, directory: &Path) -> Result<Vec<OsString>> {
+	if let Some((key, _)) = defaults.iter().find(|(key, _)| matches!(key.as_str(), "input" | "inputs" | "output")) {
+		bail!("{} has to be given in each [[image]] table", key);
+	}
+
+	let mut atlas = false;
+	let (mut options, mut inputs, mut output) = (Vec::new(), Vec::new(), None);
+	let inherited = defaults.iter().filter(|(key, _)| !image.contains_key(*key));
+	for (key, value) in image.iter().chain(inherited) {
+		match (key.as_str(), value) {
+			("input", Value::String(path)) => inputs.push(directory.join(path)),
+			("inputs", Value::Array(paths)) => {
+				for path in paths {
+					let Value::String(path) = path else {
+						bail!("inputs has to be an array of paths");
+					};
+					inputs.push(directory.join(path));
+				}
+			},
+			("output", Value::String(path)) => output = Some(directory.join(path)),
+			("input" | "output", _) => bail!("{} has to be a path", key),
+			("inputs", _) => bail!("inputs has to be an array of paths"),
+			("atlas", Value::Boolean(value)) => atlas = *value,
+			("watch", _) => bail!("watch isn't available in a manifest"),
+			(_, Value::Boolean(true)) => options.push(format!("--{}", key)),
+			(_, Value::Boolean(false)) => {},
+			// Joined with `=` so a value given to a flag is an error rather than a stray input
+			(_, Value::String(value)) => options.push(format!("--{}={}", key, value)),
+			(_, Value::Integer(value)) => options.push(format!("--{}={}", key, value)),
+			(_, Value::Float(value)) => options.push(format!("--{}={}", key, value)),
+			(_, Value::Array(_)) => bail!("{} can't be an array, only inputs can", key),
+			(_, Value::Table(_)) => bail!("{} can't be a table, only [[image]] tables are read", key),
+			(_, Value::Datetime(_)) => bail!("{} can't be a date", key)
+		}
+	}
+	let Some(output) = output else {
+		bail!("output is missing");
+	};
+	if inputs.is_empty() {
+		bail!("input or inputs is missing");
+	}
+
+	let mut command_line: Vec<OsString> = vec![
+		OsString::from(env!("CARGO_PKG_NAME")),
+		OsString::from(match atlas {
+			false => "convert",
+			true => "atlas"
+		})
+	];
+	command_line.extend(options.into_iter().map(OsString::from));
+	// Paths go after `--` so ones starting with a dash aren't taken as options
+	command_line.push(OsString::from("--"));
+	command_line.extend(inputs.into_iter().map(OsString::from));
+	command_line.push(OsString::from(output));
+	Ok(command_line)
+}
+
+/// Runs every conversion of a manifest, listing each output as it's written
+fn build(arguments: &BuildArguments) -> Result<()> {
+	let style_heading = Style::new().underlined();
+	let style_key = Style::new().bold();
+	let style_value = Style::new().bold().cyan();
+
+	let (path, force) = (&arguments.manifest, arguments.force);
+	let manifest = match fs::read_to_string(path) {
+		Ok(source) => Manifest::parse(&source).map_err(|error| anyhow!("{}, {}", path.display(), error))?,
+		Err(error) => bail!("Can't read {}, {}", path.display(), error)
+	};
+	if manifest.images.is_empty() {
+		bail!("{} has no [[image]] tables", path.display());
+	}
+	let directory = path.parent().unwrap_or(Path::new(""));
+
+	println!("{}:", style_heading.apply_to("Manifest"));
+	println!(
+		"  - {}: {}",
+		style_key.apply_to("Path"),
+		style_value.apply_to(path.display())
+	);
+	println!(
+		"  - {}: {}",
+		style_key.apply_to("Images"),
+		style_value.apply_to(manifest.images.len())
+	);
+	println!();
+	println!("{}:", style_heading.apply_to("Output files"));
+
+	for (index, image) in manifest.images.iter().enumerate() {
+		let context = |error: &dyn std::fmt::Display| anyhow!("Image {} of {}, {}", index + 1, path.display(), error);
+		let command_line = manifest_command_line(&manifest.defaults, image, directory).map_err(|error| context(&error))?;
+		let mut arguments: Arguments = match Cli::try_parse_from(command_line) {
+			Ok(Cli { command: Command::Convert(arguments) }) => arguments,
+			Ok(Cli { command: Command::Atlas(arguments) }) => arguments.into(),
+			Ok(_) => unreachable!("manifest command lines are either convert or atlas"),
+			// Only the message, without clap's usage lines
+			Err(error) => {
+				let message = error.to_string();
+				let message = message.lines().next().unwrap_or_default();
+				return Err(context(&message.strip_prefix("error: ").unwrap_or(message)));
+			}
+		};
+		arguments.force |= force;
+
+		let started = Instant::now();
+		let inputs = expand_inputs(arguments.inputs()).map_err(|error| context(&error))?;
+		let to_directory = is_directory(arguments.output());
+		// Shader pack layouts such as `shaders/lib/` may not exist yet in a fresh checkout
+		match (to_directory, arguments.output().parent()) {
+			(true, _) => fs::create_dir_all(arguments.output())?,
+			(false, Some(parent)) => fs::create_dir_all(parent)?,
+			(false, None) => {}
+		}
+		let written = convert_inputs(&arguments, &inputs, to_directory, false).map_err(|error| context(&error))?;
+
+		println!(
+			"  - {}: {}, {}",
+			style_key.apply_to(arguments.output().display()),
+			style_value.apply_to(match inputs.len() {
+				1 => inputs[0].display().to_string(),
+				count => format!("{} images", count)
+			}),
+			style_value.apply_to(match written {
+				false => String::from("up to date"),
+				true => format!("{} ms", started.elapsed().as_millis())
+			})
+		);
+	}
 	Ok(())
 }
 
+/// Inserts `convert` when the arguments don't start with a subcommand, so
+/// `image_to_glsl_array <in> <out>` keeps working
+fn with_default_command(mut arguments: Vec<OsString>) -> Vec<OsString> {
+	let command = Cli::command();
+	// Paths that aren't valid UTF-8 can't name a subcommand, so they're converted too
+	let explicit = match arguments.get(1).map(|first| first.to_str()) {
+		None | Some(Some("help" | "-h" | "--help")) => true,
+		Some(Some(first)) => command.find_subcommand(first).is_some(),
+		Some(None) => false
+	};
+	if !explicit {
+		arguments.insert(1, OsString::from("convert"));
+	}
+	arguments
+}
+
 pub fn main() -> Result<()> {
 	let style_error_heading = Style::new().underlined();
 	let style_success = Style::new().bold().green();
 	let style_error = Style::new().bold().red();
 
-	if let Err(error) = run(Arguments::parse()) {
+	let result = match Cli::parse_from(with_default_command(env::args_os().collect())).command {
+		Command::Convert(arguments) => run(arguments),
+		Command::Atlas(arguments) => run(arguments.into()),
+		Command::Decode(arguments) => decode_arrays(&arguments),
+		Command::Info(arguments) => describe_images(&arguments),
+		Command::Validate(arguments) => validate_files(&arguments),
+		Command::Build(arguments) => build(&arguments)
+	};
+	if let Err(error) = result {
 		println!("{}:\n  {}", style_error_heading.apply_to("An error occured"), style_error.apply_to(error));
 		exit(1);
 	} else {
 		println!("\n{}", style_success.apply_to("Done!"));
 		Ok(())
 	}
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{ffi::OsString, path::{Path, PathBuf}};
+	use clap::Parser;
+	use image_to_glsl_array::{manifest::Manifest, Encoding, Format};
+	use super::{manifest_command_line, with_default_command, Arguments, Cli, Command};
+
+	/// Arguments of the `index`th image of `source`, checking it parses as `convert` or `atlas`
+	fn parse(source: &str, index: usize) -> (Arguments, bool) {
+		let manifest = Manifest::parse(source).unwrap();
+		let command_line = manifest_command_line(&manifest.defaults, &manifest.images[index], Path::new("pack")).unwrap();
+		match Cli::try_parse_from(command_line).unwrap().command {
+			Command::Convert(arguments) => (arguments, false),
+			Command::Atlas(arguments) => (arguments.into(), true),
+			command => panic!("parsed as {:?}", command)
+		}
+	}
+
+	#[test]
+	fn manifest_images_parse_as_commands() {
+		let source = concat!(
+			"format = \"hlsl\"\n",
+			"flip-y = true\n",
+			"[[image]]\n",
+			"input = \"-dashed.png\"\n",
+			"output = \"out/sky.hlsl\"\n",
+			"scale = 0.5\n",
+			"resize = \"64x32\"\n",
+			"flip-y = false\n",
+			"[[image]]\n",
+			"inputs = [\"a.png\", \"b.png\"]\n",
+			"output = \"out/sprites.glsl\"\n",
+			"atlas = true\n",
+			"format = \"glsl\"\n",
+			"encoding = \"palette\"\n",
+			"palette-size = 16\n"
+		);
+
+		let (arguments, atlas) = parse(source, 0);
+		assert!(!atlas);
+		assert_eq!(arguments.inputs(), [PathBuf::from("pack/-dashed.png")]);
+		assert_eq!(arguments.output(), PathBuf::from("pack/out/sky.hlsl"));
+		assert_eq!(arguments.array.format, Format::Hlsl);
+		// The image's own keys win over the defaults
+		assert!(!arguments.array.flip_y);
+		assert_eq!(arguments.transform.scale, Some(0.5));
+		assert_eq!(arguments.transform.resize.map(|size| (size.width, size.height)), Some((64, 32)));
+
+		let (arguments, atlas) = parse(source, 1);
+		assert!(atlas && arguments.atlas);
+		assert_eq!(arguments.inputs(), [PathBuf::from("pack/a.png"), PathBuf::from("pack/b.png")]);
+		assert_eq!(arguments.array.format, Format::Glsl);
+		assert_eq!(arguments.array.encoding, Encoding::Palette);
+		assert_eq!(arguments.array.palette_size, 16);
+		assert!(arguments.array.flip_y);
+	}
+
+	#[test]
+	fn options_between_paths() {
+		let command_line = with_default_command(
+			["image_to_glsl_array", "a.png", "--helpers", "b.png", "--flip-y", "out/"].map(OsString::from).to_vec()
+		);
+		let Command::Convert(arguments) = Cli::try_parse_from(command_line).unwrap().command else {
+			panic!("not parsed as convert");
+		};
+		assert_eq!(arguments.inputs(), [PathBuf::from("a.png"), PathBuf::from("b.png")]);
+		assert_eq!(arguments.output(), Path::new("out/"));
+		assert!(arguments.array.helpers && arguments.array.flip_y);
+	}
+
+	#[test]
+	fn manifest_images_reject_invalid_keys() {
+		let error = |source: &str| {
+			let manifest = Manifest::parse(source).unwrap();
+			manifest_command_line(&manifest.defaults, &manifest.images[0], Path::new("")).unwrap_err().to_string()
+		};
+		assert_eq!(error("output = \"a.glsl\"\n[[image]]\ninput = \"a.png\"\n"), "output has to be given in each [[image]] table");
+		assert_eq!(error("[[image]]\ninput = \"a.png\"\n"), "output is missing");
+		assert_eq!(error("[[image]]\noutput = \"a.glsl\"\n"), "input or inputs is missing");
+		assert_eq!(error("[[image]]\ninput = \"a.png\"\noutput = \"a.glsl\"\nwatch = true\n"), "watch isn't available in a manifest");
+		assert_eq!(error("[[image]]\ninputs = [1]\noutput = \"a.glsl\"\n"), "inputs has to be an array of paths");
+		assert_eq!(error("[[image]]\ninput = \"a.png\"\noutput = \"a.glsl\"\nname = [\"a\"]\n"), "name can't be an array, only inputs can");
+		assert_eq!(error("[defaults]\nflip-y = true\n[[image]]\ninput = \"a.png\"\noutput = \"a.glsl\"\n"), "defaults can't be a table, only [[image]] tables are read");
+
+		// Values go through `--key=value`, so one given to a flag fails to parse
+		let manifest = Manifest::parse("[[image]]\ninput = \"a.png\"\noutput = \"a.glsl\"\nflip-y = 3\n").unwrap();
+		let command_line = manifest_command_line(&manifest.defaults, &manifest.images[0], Path::new("")).unwrap();
+		assert!(Cli::try_parse_from(command_line).is_err());
+	}
+}
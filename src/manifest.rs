@@ -0,0 +1,115 @@
+//! Reads `glsl-images.toml` manifests listing conversions to run together.
+//!
+//! A manifest is a TOML document with an `[[image]]` table per conversion. Keys outside of
+//! them apply to every image.
+//!
+//! ```toml
+//! format = "glsl"
+//! glsl-version = "330"
+//!
+//! [[image]]
+//! input = "textures/sky.png"
+//! output = "shaders/lib/sky.glsl"
+//! encoding = "palette"
+//! flip-y = true
+//! scale = 0.5
+//! ```
+
+use thiserror::Error;
+pub use toml::{Table, Value};
+
+/// Name of the manifest looked for when none is given
+pub const FILE_NAME: &str = "glsl-images.toml";
+
+#[derive(Error, Debug)]
+pub enum ManifestError {
+	#[error("{0}")]
+	Syntax(#[from] toml::de::Error),
+	#[error("image has to be written as [[image]] tables")]
+	InvalidImages
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Manifest {
+	/// Keys outside of the `[[image]]` tables, applying to every image
+	pub defaults: Table,
+	/// Keys of each `[[image]]` table
+	pub images: Vec<Table>
+}
+
+impl Manifest {
+	pub fn parse(source: &str) -> Result<Manifest, ManifestError> {
+		let mut defaults: Table = source.parse()?;
+		let images = match defaults.remove("image") {
+			None => Vec::new(),
+			Some(Value::Array(images)) => images
+				.into_iter()
+				.map(|image| match image {
+					Value::Table(image) => Ok(image),
+					_ => Err(ManifestError::InvalidImages)
+				})
+				.collect::<Result<_, _>>()?
+			,
+			Some(_) => return Err(ManifestError::InvalidImages)
+		};
+		Ok(Manifest { defaults, images })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Manifest, ManifestError, Value};
+
+	fn string(value: &str) -> Value {
+		Value::String(String::from(value))
+	}
+
+	#[test]
+	fn defaults_and_images() {
+		let manifest = Manifest::parse(concat!(
+			"# Shader pack\n",
+			"format = \"glsl\"\n",
+			"\n",
+			"[[image]]\n",
+			"input = \"sky.png\"\n",
+			"[[image]]  # second\n",
+			"input = \"sun.png\"\n",
+			"helpers = true\n"
+		)).unwrap();
+		assert_eq!(manifest.defaults.len(), 1);
+		assert_eq!(manifest.defaults["format"], string("glsl"));
+		assert_eq!(manifest.images.len(), 2);
+		assert_eq!(manifest.images[0]["input"], string("sky.png"));
+		assert_eq!(manifest.images[1]["input"], string("sun.png"));
+		assert_eq!(manifest.images[1]["helpers"], Value::Boolean(true));
+
+		assert!(Manifest::parse("format = \"glsl\"\n").unwrap().images.is_empty());
+	}
+
+	#[test]
+	fn any_toml() {
+		let manifest = Manifest::parse(concat!(
+			"image = [{ input = \"sky.png\", name = \"sky\\u00e9\" }, { input = '''C:\\textures\\sun.png''' }]\n",
+			"description = \"\"\"\n",
+			"two\n",
+			"lines\"\"\"\n",
+			"[other]\n",
+			"scale = 0.5\n"
+		)).unwrap();
+		assert_eq!(manifest.images[0]["name"], string("sky\u{e9}"));
+		assert_eq!(manifest.images[1]["input"], string("C:\\textures\\sun.png"));
+		assert_eq!(manifest.defaults["description"], string("two\nlines"));
+		assert_eq!(manifest.defaults["other"]["scale"], Value::Float(0.5));
+	}
+
+	#[test]
+	fn errors() {
+		assert!(matches!(Manifest::parse("[[image]]\nname = \"a\"\nname = \"b\"\n"), Err(ManifestError::Syntax(_))));
+		assert!(matches!(Manifest::parse("input \"sky.png\"\n"), Err(ManifestError::Syntax(_))));
+		assert!(matches!(Manifest::parse("[image]\ninput = \"sky.png\"\n"), Err(ManifestError::InvalidImages)));
+		assert!(matches!(Manifest::parse("image = \"sky.png\"\n"), Err(ManifestError::InvalidImages)));
+		assert!(matches!(Manifest::parse("image = [\"sky.png\"]\n"), Err(ManifestError::InvalidImages)));
+		// The same key may be set in each table and the defaults
+		assert!(Manifest::parse("name = \"a\"\n[[image]]\nname = \"b\"\n[[image]]\nname = \"c\"\n").is_ok());
+	}
+}
@@ -0,0 +1,180 @@
+use thiserror::Error;
+
+/// Keywords and reserved words of desktop GLSL and GLSL ES, excluding the
+/// vector, matrix, sampler and image types which are matched by `is_builtin_type`
+const RESERVED_WORDS: &[&str] = &[
+	"attribute", "const", "uniform", "varying", "buffer", "shared", "coherent", "volatile", "restrict",
+	"readonly", "writeonly", "atomic_uint", "layout", "centroid", "flat", "smooth", "noperspective",
+	"patch", "sample", "invariant", "precise", "break", "continue", "do", "for", "while", "switch", "case",
+	"default", "if", "else", "subroutine", "in", "out", "inout", "int", "void", "bool", "true", "false",
+	"float", "double", "uint", "discard", "return", "lowp", "mediump", "highp", "precision", "struct",
+	"common", "partition", "active", "asm", "class", "union", "enum", "typedef", "template", "this",
+	"resource", "goto", "inline", "noinline", "public", "static", "extern", "external", "interface",
+	"long", "short", "half", "fixed", "unsigned", "superp", "input", "output", "filter", "sizeof", "cast",
+	"namespace", "using", "sampler", "subpassInput",
+	// WGSL, so the same name works for every output format
+	"alias", "array", "atomic", "bitcast", "continuing", "diagnostic", "enable", "f16", "f32", "fallthrough",
+	"fn", "i32", "let", "loop", "override", "ptr", "requires", "u32", "var",
+	// HLSL, whose numbered scalar, vector and matrix types are matched by `is_hlsl_numeric_type`
+	"AppendStructuredBuffer", "asm_fragment", "BlendState", "Buffer", "ByteAddressBuffer", "cbuffer", "column_major",
+	"compile", "compile_fragment", "CompileShader", "ComputeShader", "ConsumeStructuredBuffer",
+	"DepthStencilState", "DepthStencilView", "DomainShader", "dword", "export", "fxgroup", "GeometryShader",
+	"groupshared", "HullShader", "InputPatch", "line", "lineadj", "linear", "LineStream", "matrix",
+	"min10float", "min12int", "min16float", "min16int", "min16uint", "nointerpolation", "NULL",
+	"OutputPatch", "packoffset", "pass", "pixelfragment", "PixelShader", "point", "PointStream",
+	"RasterizerState", "RenderTargetView", "register", "row_major", "RWBuffer", "RWByteAddressBuffer",
+	"RWStructuredBuffer", "RWTexture1D", "RWTexture1DArray", "RWTexture2D", "RWTexture2DArray",
+	"RWTexture3D", "SamplerComparisonState", "SamplerState", "snorm", "stateblock", "stateblock_state",
+	"string", "StructuredBuffer", "tbuffer", "texture", "technique", "technique10", "technique11", "Texture1D",
+	"Texture1DArray", "Texture2D", "Texture2DArray", "Texture2DMS", "Texture2DMSArray", "Texture3D",
+	"TextureCube", "TextureCubeArray", "triangle", "triangleadj", "TriangleStream", "typename", "unorm",
+	"vector", "vertexfragment", "VertexShader", "auto", "catch", "char", "const_cast", "delete",
+	"dynamic_cast", "explicit", "friend", "mutable", "new", "operator", "private", "protected",
+	"reinterpret_cast", "signed", "static_cast", "throw", "try", "virtual"
+];
+
+#[derive(Error, Debug)]
+pub enum NameError {
+	#[error("\"{0}\" is not a valid GLSL identifier, use letters, digits and underscores and don't start with a digit")]
+	InvalidCharacters(String),
+	#[error("\"{0}\" is reserved in GLSL, identifiers can't start with \"gl_\" or contain \"__\"")]
+	ReservedPrefix(String),
+	#[error("\"{0}\" is a GLSL, HLSL or WGSL keyword, reserved word or type")]
+	ReservedWord(String),
+	#[error("\"{0}\" would prefix reserved macro names, names can't start with \"GL_\" in any case, be \"gl\" or end with \"_\"")]
+	ReservedMacroPrefix(String)
+}
+
+/// Checks that `name` can be used as a GLSL identifier, and uppercased as the prefix of the
+/// include guard and size macros
+pub fn validate(name: &str) -> Result<(), NameError> {
+	let mut characters = name.chars();
+	let valid_start = characters.next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_');
+	if !valid_start || !characters.all(|character| character.is_ascii_alphanumeric() || character == '_') {
+		return Err(NameError::InvalidCharacters(name.to_string()));
+	}
+	if name.starts_with("gl_") || name.contains("__") {
+		return Err(NameError::ReservedPrefix(name.to_string()));
+	}
+	if RESERVED_WORDS.contains(&name) || is_builtin_type(name) || is_hlsl_numeric_type(name) {
+		return Err(NameError::ReservedWord(name.to_string()));
+	}
+	if is_reserved_macro_prefix(name) {
+		return Err(NameError::ReservedMacroPrefix(name.to_string()));
+	}
+	Ok(())
+}
+
+/// Clap value parser for `--name`
+pub fn parse(name: &str) -> Result<String, NameError> {
+	validate(name).map(|_| name.to_string())
+}
+
+/// Turns a file stem into a valid identifier, falling back to `image`
+pub fn sanitize(stem: &str) -> String {
+	let mut name = String::new();
+	for character in stem.chars() {
+		match character.is_ascii_alphanumeric() {
+			true => name.push(character),
+			false => if !name.is_empty() && !name.ends_with('_') {
+				name.push('_');
+			}
+		}
+	}
+	while name.ends_with('_') {
+		name.pop();
+	}
+
+	if name.is_empty() {
+		return String::from("image");
+	}
+	if name.starts_with(|first: char| first.is_ascii_digit()) || is_reserved_macro_prefix(&name) {
+		name.insert_str(0, "image_");
+	}
+	if validate(&name).is_err() {
+		name += "_image";
+	}
+	name
+}
+
+/// Whether macros such as `<NAME>_WIDTH` would start with `GL_` or hold `__`, both reserved
+/// by the GLSL preprocessor
+fn is_reserved_macro_prefix(name: &str) -> bool {
+	(name.to_ascii_uppercase() + "_").starts_with("GL_") || name.ends_with('_')
+}
+
+/// `vec3`, `dmat4x2`, `usampler2DArray`, `image3D` and the like
+fn is_builtin_type(name: &str) -> bool {
+	let vector = name.strip_prefix(['b', 'i', 'u', 'd']).unwrap_or(name);
+	if vector.strip_prefix("vec").is_some_and(|size| matches!(size, "2" | "3" | "4")) {
+		return true;
+	}
+
+	let matrix = name.strip_prefix('d').unwrap_or(name);
+	if let Some(size) = matrix.strip_prefix("mat") {
+		let bytes = size.as_bytes();
+		let dimension = |byte: u8| (b'2'..=b'4').contains(&byte);
+		return match bytes.len() {
+			1 => dimension(bytes[0]),
+			3 => dimension(bytes[0]) && bytes[1] == b'x' && dimension(bytes[2]),
+			_ => false
+		};
+	}
+
+	let opaque = [Some(name), name.strip_prefix(['i', 'u'])];
+	opaque.into_iter().flatten().any(|candidate| {
+		["sampler", "image", "texture", "subpassInput"]
+			.iter()
+			.filter_map(|prefix| candidate.strip_prefix(prefix))
+			.any(|rest| {
+				rest.starts_with(|first: char| first.is_ascii_digit())
+					|| rest.starts_with("Cube")
+					|| rest.starts_with("Buffer")
+					|| rest.starts_with("MS")
+					|| rest == "Shadow"
+			})
+	})
+}
+
+/// HLSL `float4`, `int2x3`, `min16uint1` and the like, built from a scalar type and one or two sizes of 1 to 4
+fn is_hlsl_numeric_type(name: &str) -> bool {
+	let scalars = ["float", "int", "uint", "half", "bool", "double", "dword", "min16float", "min10float", "min16int", "min12int", "min16uint"];
+	scalars.iter().filter_map(|scalar| name.strip_prefix(scalar)).any(|size| {
+		let bytes = size.as_bytes();
+		let dimension = |byte: u8| (b'1'..=b'4').contains(&byte);
+		match bytes.len() {
+			1 => dimension(bytes[0]),
+			3 => dimension(bytes[0]) && bytes[1] == b'x' && dimension(bytes[2]),
+			_ => false
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{sanitize, validate, NameError};
+
+	#[test]
+	fn macro_prefixes() {
+		for name in ["GL_sky", "Gl_sky", "gL", "sky_"] {
+			assert!(matches!(validate(name), Err(NameError::ReservedMacroPrefix(_))), "{} was accepted", name);
+		}
+		assert!(matches!(validate("gl_sky"), Err(NameError::ReservedPrefix(_))));
+		for name in ["sky", "glow", "gla_sky", "sky_1", "_sky"] {
+			assert!(validate(name).is_ok(), "{} was rejected", name);
+		}
+	}
+
+	#[test]
+	fn sanitized_names_are_valid() {
+		assert_eq!(sanitize("GL-sky"), "image_GL_sky");
+		assert_eq!(sanitize("gl"), "image_gl");
+		assert_eq!(sanitize("sky-"), "sky");
+		assert_eq!(sanitize("glow map"), "glow_map");
+		assert_eq!(sanitize("2d sky"), "image_2d_sky");
+		assert_eq!(sanitize("vec4"), "vec4_image");
+		for stem in ["GL-sky", "gl", "sky-", "__", "float4", "in", "Gl__x"] {
+			assert!(validate(&sanitize(stem)).is_ok(), "{} gave {}", stem, sanitize(stem));
+		}
+	}
+}
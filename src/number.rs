@@ -0,0 +1,57 @@
+use std::fmt::Write;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Formats GLSL literals into a reused buffer instead of allocating a new `String` per value
+#[derive(Default)]
+pub struct NumberFormatter {
+	buffer: String
+}
+
+impl NumberFormatter {
+	/// `vec4(r, g, b, a)` with 7 decimals per component
+	pub fn vec4(&mut self, color: [f64; 4]) -> &str {
+		self.vector4("vec4", color)
+	}
+
+	/// `float4(r, g, b, a)` with 7 decimals per component
+	pub fn float4(&mut self, color: [f64; 4]) -> &str {
+		self.vector4("float4", color)
+	}
+
+	/// `vec4<f32>(r, g, b, a)` with 7 decimals per component
+	pub fn vec4_f32(&mut self, color: [f64; 4]) -> &str {
+		self.vector4("vec4<f32>", color)
+	}
+
+	fn vector4(&mut self, constructor: &str, color: [f64; 4]) -> &str {
+		self.buffer.clear();
+		// Writing into a `String` can't fail
+		let _ = write!(
+			self.buffer,
+			"{}({:.7}, {:.7}, {:.7}, {:.7})",
+			constructor,
+			color[0],
+			color[1],
+			color[2],
+			color[3]
+		);
+		&self.buffer
+	}
+
+	/// `0xAARRGGBBu` of an 8-bit RGBA color
+	pub fn packed_rgba8(&mut self, color: [u8; 4]) -> &str {
+		self.hex(u32::from_be_bytes([color[3], color[0], color[1], color[2]]))
+	}
+
+	/// Unsigned `0x........u` literal, always 8 digits wide
+	pub fn hex(&mut self, value: u32) -> &str {
+		self.buffer.clear();
+		self.buffer.push_str("0x");
+		for shift in (0..8).rev() {
+			self.buffer.push(HEX_DIGITS[((value >> (shift * 4)) & 0xF) as usize] as char);
+		}
+		self.buffer.push('u');
+		&self.buffer
+	}
+}
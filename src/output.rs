@@ -0,0 +1,92 @@
+use std::{
+	fs::{self, File, OpenOptions},
+	io::{self, BufWriter, Write},
+	path::{Path, PathBuf}
+};
+
+/// Output written to a hidden `.<file name>.partial` file next to it and moved into place by
+/// `finish`, so a failed conversion leaves the previous file as it was instead of a partial one
+/// carrying a matching source hash. The temporary file is removed when dropped unfinished
+pub struct PendingOutput {
+	writer: BufWriter<File>,
+	temporary: PathBuf,
+	path: PathBuf,
+	finished: bool
+}
+
+impl PendingOutput {
+	pub fn create(path: &Path) -> io::Result<Self> {
+		let file_name = path.file_name().ok_or_else(|| {
+			io::Error::new(io::ErrorKind::InvalidInput, format!("{} isn't a file path", path.display()))
+		})?;
+		let temporary = path.with_file_name(format!(".{}.partial", file_name.to_string_lossy()));
+		let file = OpenOptions::new()
+			.create(true)
+			.write(true)
+			.read(false)
+			.truncate(true)
+			.open(&temporary)?
+		;
+		Ok(PendingOutput {
+			writer: BufWriter::new(file),
+			temporary,
+			path: path.to_path_buf(),
+			finished: false
+		})
+	}
+
+	/// Flushes the output and replaces the file at the path it was created for
+	pub fn finish(mut self) -> io::Result<()> {
+		self.writer.flush()?;
+		fs::rename(&self.temporary, &self.path)?;
+		self.finished = true;
+		Ok(())
+	}
+}
+
+impl Write for PendingOutput {
+	fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+		self.writer.write(bytes)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.writer.flush()
+	}
+}
+
+impl Drop for PendingOutput {
+	fn drop(&mut self) {
+		if !self.finished {
+			let _ = fs::remove_file(&self.temporary);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{env, fs, io::Write, process};
+	use super::PendingOutput;
+
+	#[test]
+	fn replaced_only_when_finished() {
+		let directory = env::temp_dir().join(format!("pending_output_{}", process::id()));
+		fs::create_dir_all(&directory).unwrap();
+		let path = directory.join("sky.glsl");
+		fs::write(&path, "previous").unwrap();
+
+		let mut output = PendingOutput::create(&path).unwrap();
+		write!(output, "partial").unwrap();
+		assert!(directory.join(".sky.glsl.partial").exists());
+		drop(output);
+		assert_eq!(fs::read_to_string(&path).unwrap(), "previous");
+		assert!(!directory.join(".sky.glsl.partial").exists());
+
+		let mut output = PendingOutput::create(&path).unwrap();
+		write!(output, "complete").unwrap();
+		output.finish().unwrap();
+		assert_eq!(fs::read_to_string(&path).unwrap(), "complete");
+		assert_eq!(fs::read_dir(&directory).unwrap().count(), 1);
+
+		fs::remove_dir_all(&directory).unwrap();
+	}
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use crate::pixels::Pixels;
+
+/// Reduced set of colors an image is indexed against
+pub struct Palette {
+	pub colors: Vec<[u8; 4]>,
+	lookup: HashMap<[u8; 4], usize>
+}
+
+impl Palette {
+	/// Collects the unique colors of `pixels`, falling back to median-cut quantization
+	/// when there are more than `max_colors` of them
+	pub fn build(pixels: &Pixels, max_colors: usize) -> Palette {
+		let dimensions = pixels.dimensions();
+		let mut counts: HashMap<[u8; 4], u64> = HashMap::new();
+		for y in 0..dimensions.1 {
+			for x in 0..dimensions.0 {
+				*counts.entry(pixels.get_rgba8(x, y)).or_insert(0) += 1;
+			}
+		}
+
+		let mut histogram: Vec<([u8; 4], u64)> = counts.into_iter().collect();
+		histogram.sort_unstable();
+
+		if histogram.len() <= max_colors {
+			let colors: Vec<[u8; 4]> = histogram.into_iter().map(|(color, _)| color).collect();
+			let lookup = colors.iter().enumerate().map(|(index, color)| (*color, index)).collect();
+			return Palette { colors, lookup };
+		}
+
+		let mut boxes = vec![histogram];
+		while boxes.len() < max_colors {
+			let widest = boxes
+				.iter()
+				.enumerate()
+				.filter(|(_, colors)| colors.len() > 1)
+				.map(|(index, colors)| (index, widest_channel(colors)))
+				.max_by_key(|(_, (_, range))| *range)
+			;
+			let Some((index, (channel, _))) = widest else {
+				break;
+			};
+
+			let mut colors = boxes.swap_remove(index);
+			colors.sort_unstable_by_key(|(color, _)| color[channel]);
+			let upper = colors.split_off(median_split(&colors));
+			boxes.push(colors);
+			boxes.push(upper);
+		}
+
+		let mut colors = Vec::with_capacity(boxes.len());
+		let mut lookup = HashMap::new();
+		for (index, members) in boxes.iter().enumerate() {
+			colors.push(weighted_average(members));
+			for (color, _) in members {
+				lookup.insert(*color, index);
+			}
+		}
+
+		Palette { colors, lookup }
+	}
+
+	/// Index of the palette entry `color` was mapped to
+	pub fn index_of(&self, color: [u8; 4]) -> usize {
+		self.lookup[&color]
+	}
+
+	/// Smallest of 1, 2, 4 or 8 bits that can address every palette entry
+	pub fn bits_per_index(&self) -> u32 {
+		bits_per_index(self.colors.len())
+	}
+}
+
+/// `Palette::bits_per_index` for a palette of `colors` entries
+pub(crate) fn bits_per_index(colors: usize) -> u32 {
+	match colors {
+		0..=2 => 1,
+		3..=4 => 2,
+		5..=16 => 4,
+		_ => 8
+	}
+}
+
+fn widest_channel(colors: &[([u8; 4], u64)]) -> (usize, u8) {
+	(0..4)
+		.map(|channel| {
+			let min = colors.iter().map(|(color, _)| color[channel]).min().unwrap_or(0);
+			let max = colors.iter().map(|(color, _)| color[channel]).max().unwrap_or(0);
+			(channel, max - min)
+		})
+		.max_by_key(|(_, range)| *range)
+		.unwrap()
+}
+
+/// Position splitting `colors` (sorted along one channel) into two halves of similar pixel count
+fn median_split(colors: &[([u8; 4], u64)]) -> usize {
+	let total: u64 = colors.iter().map(|(_, count)| count).sum();
+	let mut running = 0;
+	for (index, (_, count)) in colors.iter().enumerate() {
+		running += count;
+		if running * 2 >= total {
+			return (index + 1).clamp(1, colors.len() - 1);
+		}
+	}
+	colors.len() - 1
+}
+
+fn weighted_average(colors: &[([u8; 4], u64)]) -> [u8; 4] {
+	let total: u64 = colors.iter().map(|(_, count)| count).sum();
+	let mut average = [0u8; 4];
+	for (channel, value) in average.iter_mut().enumerate() {
+		let sum: u64 = colors.iter().map(|(color, count)| (color[channel] as u64) * count).sum();
+		*value = ((sum + total / 2) / total) as u8;
+	}
+	average
+}
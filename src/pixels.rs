@@ -0,0 +1,96 @@
+use image::{ColorType, DynamicImage, ImageBuffer, Rgba, Rgba32FImage, RgbaImage};
+use crate::color::ColorConversion;
+
+/// Decoded pixel data, keeping the 16-bit or floating point precision of the source
+enum Samples {
+	Rgba8(RgbaImage),
+	Rgba16(ImageBuffer<Rgba<u16>, Vec<u16>>),
+	Rgba32F(Rgba32FImage)
+}
+
+/// Source image pixels as read by the encoders, with the color conversion applied
+pub struct Pixels {
+	samples: Samples,
+	conversion: ColorConversion
+}
+
+impl Pixels {
+	pub fn from_image(image: &DynamicImage, conversion: ColorConversion) -> Pixels {
+		let samples = match image.color() {
+			ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8 => Samples::Rgba8(image.to_rgba8()),
+			ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16 => Samples::Rgba16(image.to_rgba16()),
+			_ => Samples::Rgba32F(image.to_rgba32f())
+		};
+		Pixels { samples, conversion }
+	}
+
+	pub fn dimensions(&self) -> (u32, u32) {
+		match &self.samples {
+			Samples::Rgba8(image) => image.dimensions(),
+			Samples::Rgba16(image) => image.dimensions(),
+			Samples::Rgba32F(image) => image.dimensions()
+		}
+	}
+
+	/// Whether values can fall outside of 0..1, as with Radiance HDR and OpenEXR images,
+	/// which also store linear rather than sRGB encoded colors
+	pub fn is_float(&self) -> bool {
+		matches!(self.samples, Samples::Rgba32F(_))
+	}
+
+	/// Channels and value range of `get`, such as `linear RGBA, 0..1 value range`
+	pub fn value_format(&self) -> String {
+		format!(
+			"{}, {}",
+			self.conversion.describe(),
+			match self.is_float() {
+				false => "0..1 value range",
+				true => "unclamped HDR values"
+			}
+		)
+	}
+
+	/// RGBA of the pixel at `x`, `y` with 1.0 being full intensity
+	pub fn get(&self, x: u32, y: u32) -> [f64; 4] {
+		let color = match &self.samples {
+			Samples::Rgba8(image) => image.get_pixel(x, y).0.map(|value| 1.0 / 255.0 * (value as f64)),
+			Samples::Rgba16(image) => image.get_pixel(x, y).0.map(|value| 1.0 / 65535.0 * (value as f64)),
+			Samples::Rgba32F(image) => image.get_pixel(x, y).0.map(|value| value as f64)
+		};
+		match self.conversion.is_identity() {
+			true => color,
+			false => self.conversion.apply(color, self.is_float())
+		}
+	}
+
+	/// RGBA of the pixel at `x`, `y` clamped and rounded to 8 bits per channel
+	pub fn get_rgba8(&self, x: u32, y: u32) -> [u8; 4] {
+		match (&self.samples, self.conversion.is_identity()) {
+			(Samples::Rgba8(image), true) => image.get_pixel(x, y).0,
+			_ => quantize(self.get(x, y))
+		}
+	}
+}
+
+/// Clamps each channel to 0..1 and rounds it to 8 bits
+pub fn quantize(color: [f64; 4]) -> [u8; 4] {
+	color.map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+/// `image` as RGBA with 8-bit, 16-bit or floating point channels, whichever keeps the precision of `color`
+pub fn to_rgba_like(image: &DynamicImage, color: ColorType) -> DynamicImage {
+	match color {
+		ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8 => DynamicImage::ImageRgba8(image.to_rgba8()),
+		ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16 => DynamicImage::ImageRgba16(image.to_rgba16()),
+		_ => DynamicImage::ImageRgba32F(image.to_rgba32f())
+	}
+}
+
+/// Color type and per-channel bit depth of a decoded image, e.g. `Rgba16, 16-bit`
+pub fn describe_color(color: ColorType) -> String {
+	format!(
+		"{:?}, {}-bit",
+		color,
+		color.bits_per_pixel() / (color.channel_count() as u16)
+	)
+}
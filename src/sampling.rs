@@ -0,0 +1,67 @@
+/// How out-of-range texel coordinates are mapped back into the image
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WrapMode {
+	/// Clamp to the nearest edge texel
+	Clamp,
+	/// Tile the image
+	Repeat,
+	/// Tile the image, mirroring every other repetition
+	Mirror
+}
+
+impl WrapMode {
+	pub fn name(&self) -> &'static str {
+		match self {
+			WrapMode::Clamp => "clamp",
+			WrapMode::Repeat => "repeat",
+			WrapMode::Mirror => "mirror"
+		}
+	}
+}
+
+/// Generates `<name>_fetch`, `<name>_sample_nearest` and `<name>_sample_bilinear` on top of
+/// `texel`, a GLSL expression reading the in-range texel at `ivec2 coord`
+pub fn helpers(name: &str, dimensions: (u32, u32), texel: &str, wrap: WrapMode) -> String {
+	let mut output = String::new();
+
+	output += &format!("\nconst ivec2 {}_size = ivec2({}, {});\n", name, dimensions.0, dimensions.1)[..];
+
+	// `%` is undefined for negative operands and integer `clamp`/`min` need GLSL 1.30,
+	// so wrapping goes through float math to stay portable across versions
+	output += &format!("\nivec2 {}_wrap(ivec2 coord) {{\n", name)[..];
+	output += &match wrap {
+		WrapMode::Clamp => format!("\treturn ivec2(clamp(vec2(coord), vec2(0.0), vec2({}_size - 1)));\n", name),
+		WrapMode::Repeat => format!("\treturn coord - {0}_size * ivec2(floor(vec2(coord) / vec2({0}_size)));\n", name),
+		WrapMode::Mirror => format!(
+			concat!(
+				"\tivec2 period = {}_size * 2;\n",
+				"\tivec2 wrapped = coord - period * ivec2(floor(vec2(coord) / vec2(period)));\n",
+				"\treturn ivec2(min(vec2(wrapped), vec2(period - 1 - wrapped)));\n"
+			),
+			name
+		)
+	}[..];
+	output += "}\n";
+
+	output += &format!("\nvec4 {}_fetch(ivec2 coord) {{\n", name)[..];
+	output += &format!("\tcoord = {}_wrap(coord);\n", name)[..];
+	output += &format!("\treturn {};\n", texel)[..];
+	output += "}\n";
+
+	output += &format!("\nvec4 {}_sample_nearest(vec2 uv) {{\n", name)[..];
+	output += &format!("\treturn {0}_fetch(ivec2(floor(uv * vec2({0}_size))));\n", name)[..];
+	output += "}\n";
+
+	output += &format!("\nvec4 {}_sample_bilinear(vec2 uv) {{\n", name)[..];
+	output += &format!("\tvec2 position = uv * vec2({}_size) - 0.5;\n", name)[..];
+	output += "\tivec2 base = ivec2(floor(position));\n";
+	output += "\tvec2 weight = fract(position);\n";
+	output += "\treturn mix(\n";
+	output += &format!("\t\tmix({0}_fetch(base), {0}_fetch(base + ivec2(1, 0)), weight.x),\n", name)[..];
+	output += &format!("\t\tmix({0}_fetch(base + ivec2(0, 1)), {0}_fetch(base + ivec2(1, 1)), weight.x),\n", name)[..];
+	output += "\t\tweight.y\n";
+	output += "\t);\n";
+	output += "}\n";
+
+	output
+}
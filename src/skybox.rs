@@ -0,0 +1,311 @@
+use std::{f32::consts::PI, io::{self, Write}};
+use image::{imageops, DynamicImage, GenericImageView, Rgba, Rgba32FImage};
+use crate::{encoding::Format, pixels, ConvertError, ConvertOptions, PixelOrder};
+
+/// How the directions around the viewer map onto a skybox image
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Projection {
+	/// Six square faces stacked top to bottom in +X, -X, +Y, -Y, +Z, -Z order, each oriented like an OpenGL cubemap face
+	Cubemap,
+	/// One panorama covering 360° horizontally and 180° vertically, its center column looking towards -Z
+	Equirectangular
+}
+
+impl Projection {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Projection::Cubemap => "cubemap",
+			Projection::Equirectangular => "equirectangular"
+		}
+	}
+}
+
+/// File name endings of each cubemap face, in stacking order
+const FACE_SUFFIXES: [[&str; 2]; 6] = [
+	["px", "posx"],
+	["nx", "negx"],
+	["py", "posy"],
+	["ny", "negy"],
+	["pz", "posz"],
+	["nz", "negz"]
+];
+
+/// Cubemap face held by a file named `stem`, going by endings such as `_px` or `-negz`
+pub fn face_index(stem: &str) -> Option<usize> {
+	let stem = stem.to_ascii_lowercase();
+	FACE_SUFFIXES.iter().position(|suffixes| {
+		suffixes.iter().any(|suffix| {
+			stem.strip_suffix(suffix)
+				.is_some_and(|rest| rest.is_empty() || rest.ends_with(['_', '-', '.']))
+		})
+	})
+}
+
+/// Stacks six square faces of equal size, in +X, -X, +Y, -Y, +Z, -Z order, into one cubemap image
+pub fn stack_faces(faces: &[DynamicImage]) -> Result<DynamicImage, ConvertError> {
+	if faces.len() != 6 {
+		return Err(ConvertError::Skybox("it takes six cubemap faces or one panorama"));
+	}
+	let size = faces[0].width();
+	if faces.iter().any(|face| face.dimensions() != (size, size)) {
+		return Err(ConvertError::Skybox("the cubemap faces have to be square and equally large"));
+	}
+
+	let mut stacked = Rgba32FImage::new(size, size * 6);
+	for (index, face) in faces.iter().enumerate() {
+		imageops::replace(&mut stacked, &face.to_rgba32f(), 0, (index as i64) * (size as i64));
+	}
+	// Keep the precision of the most precise face
+	let color = faces
+		.iter()
+		.map(|face| face.color())
+		.max_by_key(|color| color.bytes_per_pixel() / color.channel_count())
+		.unwrap()
+	;
+	Ok(pixels::to_rgba_like(&DynamicImage::ImageRgba32F(stacked), color))
+}
+
+/// Reprojects a skybox image with bilinear filtering, keeping the precision of the source.
+/// Cubemap faces become a panorama 4 faces wide and 2 tall, and a panorama becomes faces
+/// a quarter of its width.
+pub fn reproject(image: &DynamicImage, from: Projection, to: Projection) -> DynamicImage {
+	if from == to {
+		return image.clone();
+	}
+
+	let source = image.to_rgba32f();
+	let reprojected = match to {
+		Projection::Equirectangular => {
+			let size = (image.width() * 4, image.width() * 2);
+			Rgba32FImage::from_fn(size.0, size.1, |x, y| {
+				let direction = equirectangular_direction(
+					((x as f32) + 0.5) / (size.0 as f32),
+					((y as f32) + 0.5) / (size.1 as f32)
+				);
+				sample_cubemap(&source, direction)
+			})
+		},
+		Projection::Cubemap => {
+			let size = (image.width() / 4).max(1);
+			Rgba32FImage::from_fn(size, size * 6, |x, y| {
+				let direction = cubemap_direction(
+					(y / size) as usize,
+					((x as f32) + 0.5) / (size as f32),
+					(((y % size) as f32) + 0.5) / (size as f32)
+				);
+				sample_equirectangular(&source, direction)
+			})
+		}
+	};
+	pixels::to_rgba_like(&DynamicImage::ImageRgba32F(reprojected), image.color())
+}
+
+/// Direction through `u`, `v` of cubemap face `face`, with `v` = 0 being the top row
+fn cubemap_direction(face: usize, u: f32, v: f32) -> [f32; 3] {
+	let (s, t) = (u * 2.0 - 1.0, v * 2.0 - 1.0);
+	match face {
+		0 => [1.0, -t, -s],
+		1 => [-1.0, -t, s],
+		2 => [s, 1.0, t],
+		3 => [s, -1.0, -t],
+		4 => [s, -t, 1.0],
+		_ => [-s, -t, -1.0]
+	}
+}
+
+/// Face and `u`, `v` within it seen in `direction`, the inverse of `cubemap_direction`
+fn cubemap_coordinates(direction: [f32; 3]) -> (usize, f32, f32) {
+	let [x, y, z] = direction;
+	let magnitude = direction.map(f32::abs);
+	let (face, s, t, major) = if magnitude[0] >= magnitude[1] && magnitude[0] >= magnitude[2] {
+		match x > 0.0 {
+			false => (1, z, -y, magnitude[0]),
+			true => (0, -z, -y, magnitude[0])
+		}
+	} else if magnitude[1] >= magnitude[2] {
+		match y > 0.0 {
+			false => (3, x, -z, magnitude[1]),
+			true => (2, x, z, magnitude[1])
+		}
+	} else {
+		match z > 0.0 {
+			false => (5, -x, -y, magnitude[2]),
+			true => (4, x, -y, magnitude[2])
+		}
+	};
+	(face, (s / major + 1.0) / 2.0, (t / major + 1.0) / 2.0)
+}
+
+/// Direction through `u`, `v` of a panorama, with `v` = 0 looking straight up
+fn equirectangular_direction(u: f32, v: f32) -> [f32; 3] {
+	let (longitude, polar) = ((u - 0.5) * 2.0 * PI, v * PI);
+	[polar.sin() * longitude.sin(), polar.cos(), -polar.sin() * longitude.cos()]
+}
+
+fn sample_cubemap(cubemap: &Rgba32FImage, direction: [f32; 3]) -> Rgba<f32> {
+	let size = cubemap.width();
+	let (face, u, v) = cubemap_coordinates(direction);
+	// Clamped to the face rather than continuing onto its neighbour
+	bilinear(cubemap, u * (size as f32), v * (size as f32), |x, y| {
+		(
+			x.clamp(0, (size as i64) - 1) as u32,
+			(face as u32) * size + (y.clamp(0, (size as i64) - 1) as u32)
+		)
+	})
+}
+
+fn sample_equirectangular(panorama: &Rgba32FImage, direction: [f32; 3]) -> Rgba<f32> {
+	let [x, y, z] = direction;
+	let length = (x * x + y * y + z * z).sqrt();
+	let u = x.atan2(-z) / (2.0 * PI) + 0.5;
+	let v = (y / length).clamp(-1.0, 1.0).acos() / PI;
+	let (width, height) = panorama.dimensions();
+	// Longitude wraps around, latitude stops at the poles
+	bilinear(panorama, u * (width as f32), v * (height as f32), |x, y| {
+		(
+			x.rem_euclid(width as i64) as u32,
+			y.clamp(0, (height as i64) - 1) as u32
+		)
+	})
+}
+
+/// Interpolates the 4 texels around `x`, `y`, with `index` mapping texel coordinates into the image
+fn bilinear(image: &Rgba32FImage, x: f32, y: f32, index: impl Fn(i64, i64) -> (u32, u32)) -> Rgba<f32> {
+	let (x, y) = (x - 0.5, y - 0.5);
+	let (left, top) = (x.floor(), y.floor());
+	let (weight_x, weight_y) = (x - left, y - top);
+	let texel = |offset_x: i64, offset_y: i64| {
+		let (x, y) = index((left as i64) + offset_x, (top as i64) + offset_y);
+		image.get_pixel(x, y).0
+	};
+	let (top_left, top_right, bottom_left, bottom_right) = (texel(0, 0), texel(1, 0), texel(0, 1), texel(1, 1));
+
+	let mut color = [0.0; 4];
+	for channel in 0..4 {
+		let upper = top_left[channel] + (top_right[channel] - top_left[channel]) * weight_x;
+		let lower = bottom_left[channel] + (bottom_right[channel] - bottom_left[channel]) * weight_x;
+		color[channel] = upper + (lower - upper) * weight_y;
+	}
+	Rgba(color)
+}
+
+/// Checks that a cubemap image is six square faces stacked top to bottom
+pub(crate) fn validate(skybox: Option<Projection>, dimensions: (u32, u32)) -> Result<(), ConvertError> {
+	match skybox == Some(Projection::Cubemap) && dimensions.1 != dimensions.0 * 6 {
+		false => Ok(()),
+		true => Err(ConvertError::Skybox("a cubemap image has to be six square faces stacked top to bottom"))
+	}
+}
+
+/// `<name>_sample`, reading the texel seen in a direction through `texel`
+pub(crate) fn write_sample_function(output: &mut dyn Write, options: &ConvertOptions, order: PixelOrder, texel: &str) -> io::Result<()> {
+	let Some(projection) = options.skybox else {
+		return Ok(());
+	};
+	let name = &options.name;
+	let (width, height) = order.dimensions;
+	// WGSL reads through `wrapped` and can't modify parameters
+	let coord = match options.format {
+		Format::Glsl | Format::Hlsl => "coord",
+		Format::Wgsl => "wrapped"
+	};
+
+	writeln!(output, "\n// Skybox texel seen in `direction`, which doesn't need to be normalized")?;
+	match options.format {
+		Format::Glsl => writeln!(output, "vec4 {}_sample(vec3 direction) {{", name)?,
+		Format::Hlsl => writeln!(output, "float4 {}_sample(float3 direction) {{", name)?,
+		Format::Wgsl => writeln!(output, "fn {}_sample(direction: vec3<f32>) -> vec4<f32> {{", name)?
+	}
+
+	match (projection, options.format) {
+		(Projection::Cubemap, Format::Glsl | Format::Hlsl) => {
+			let (vec2, ivec2) = match options.format {
+				Format::Glsl => ("vec2", "ivec2"),
+				_ => ("float2", "int2")
+			};
+			writeln!(
+				output,
+				"\t{} magnitude = abs(direction);\n\tint face;\n\t{} uv;",
+				match options.format {
+					Format::Glsl => "vec3",
+					_ => "float3"
+				},
+				vec2
+			)?;
+			writeln!(output, "\tif (magnitude.x >= magnitude.y && magnitude.x >= magnitude.z) {{")?;
+			writeln!(output, "\t\tface = direction.x > 0.0 ? 0 : 1;")?;
+			writeln!(output, "\t\tuv = {}(direction.x > 0.0 ? -direction.z : direction.z, -direction.y) / magnitude.x;", vec2)?;
+			writeln!(output, "\t}} else if (magnitude.y >= magnitude.z) {{")?;
+			writeln!(output, "\t\tface = direction.y > 0.0 ? 2 : 3;")?;
+			writeln!(output, "\t\tuv = {}(direction.x, direction.y > 0.0 ? direction.z : -direction.z) / magnitude.y;", vec2)?;
+			writeln!(output, "\t}} else {{")?;
+			writeln!(output, "\t\tface = direction.z > 0.0 ? 4 : 5;")?;
+			writeln!(output, "\t\tuv = {}(direction.z > 0.0 ? direction.x : -direction.x, -direction.y) / magnitude.z;", vec2)?;
+			writeln!(output, "\t}}")?;
+			writeln!(output, "\t{0} coord = {0}(min(floor((uv * 0.5 + 0.5) * {1}.0), {2}.0));", ivec2, width, width - 1)?;
+			writeln!(output, "\tcoord.y += face * {};", width)?;
+		},
+		(Projection::Cubemap, Format::Wgsl) => {
+			writeln!(output, "\tlet magnitude = abs(direction);\n\tvar face: i32;\n\tvar uv: vec2<f32>;")?;
+			writeln!(output, "\tif (magnitude.x >= magnitude.y && magnitude.x >= magnitude.z) {{")?;
+			writeln!(output, "\t\tface = select(1, 0, direction.x > 0.0);")?;
+			writeln!(output, "\t\tuv = vec2<f32>(select(direction.z, -direction.z, direction.x > 0.0), -direction.y) / magnitude.x;")?;
+			writeln!(output, "\t}} else if (magnitude.y >= magnitude.z) {{")?;
+			writeln!(output, "\t\tface = select(3, 2, direction.y > 0.0);")?;
+			writeln!(output, "\t\tuv = vec2<f32>(direction.x, select(-direction.z, direction.z, direction.y > 0.0)) / magnitude.y;")?;
+			writeln!(output, "\t}} else {{")?;
+			writeln!(output, "\t\tface = select(5, 4, direction.z > 0.0);")?;
+			writeln!(output, "\t\tuv = vec2<f32>(select(-direction.x, direction.x, direction.z > 0.0), -direction.y) / magnitude.z;")?;
+			writeln!(output, "\t}}")?;
+			writeln!(output, "\tvar wrapped = vec2<i32>(min(floor((uv * 0.5 + 0.5) * {}.0), vec2<f32>({}.0)));", width, width - 1)?;
+			writeln!(output, "\twrapped.y += face * {};", width)?;
+		},
+		(Projection::Equirectangular, Format::Glsl | Format::Hlsl) => {
+			let (vec2, ivec2, atan) = match options.format {
+				Format::Glsl => ("vec2", "ivec2", "atan"),
+				_ => ("float2", "int2", "atan2")
+			};
+			writeln!(output, "\tdirection = normalize(direction);")?;
+			writeln!(
+				output,
+				"\t{0} uv = {0}({1}(direction.x, -direction.z) / 6.2831853 + 0.5, acos(clamp(direction.y, -1.0, 1.0)) / 3.1415927);",
+				vec2,
+				atan
+			)?;
+			writeln!(
+				output,
+				"\t{0} coord = {0}(min(floor(uv * {1}({2}.0, {3}.0)), {1}({4}.0, {5}.0)));",
+				ivec2,
+				vec2,
+				width,
+				height,
+				width - 1,
+				height - 1
+			)?;
+		},
+		(Projection::Equirectangular, Format::Wgsl) => {
+			writeln!(output, "\tlet normalized = normalize(direction);")?;
+			writeln!(
+				output,
+				"\tlet uv = vec2<f32>(atan2(normalized.x, -normalized.z) / 6.2831853 + 0.5, acos(clamp(normalized.y, -1.0, 1.0)) / 3.1415927);"
+			)?;
+			writeln!(
+				output,
+				"\tvar wrapped = vec2<i32>(min(floor(uv * vec2<f32>({}.0, {}.0)), vec2<f32>({}.0, {}.0)));",
+				width,
+				height,
+				width - 1,
+				height - 1
+			)?;
+		}
+	}
+
+	// Flipped outputs store the image mirrored
+	if order.flip_x {
+		writeln!(output, "\t{0}.x = {1} - {0}.x;", coord, width - 1)?;
+	}
+	if order.flip_y {
+		writeln!(output, "\t{0}.y = {1} - {0}.y;", coord, height - 1)?;
+	}
+	writeln!(output, "\treturn {};\n}}", texel)
+}
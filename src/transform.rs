@@ -0,0 +1,348 @@
+use std::str::FromStr;
+use image::{imageops::FilterType, DynamicImage, GenericImage, GenericImageView};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TransformError {
+	#[error("\"{0}\" is not a size, write it as WIDTHxHEIGHT such as 64x64")]
+	InvalidSize(String),
+	#[error("\"{0}\" is not a region, write it as X,Y,WIDTH,HEIGHT such as 0,0,64,64")]
+	InvalidRegion(String),
+	#[error("\"{0}\" is not a scale factor, use a number above 0 such as 0.5")]
+	InvalidScale(String),
+	#[error("The crop region {region} doesn't fit in the {width} x {height} image")]
+	CropOutside {
+		region: Region,
+		width: u32,
+		height: u32
+	}
+}
+
+/// Filter images are resampled with, mirroring `image::imageops::FilterType`
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Filter {
+	/// Nearest texel, keeping the hard edges of pixel art
+	Nearest,
+	/// Linear
+	Triangle,
+	/// Cubic
+	CatmullRom,
+	/// Gaussian, slightly blurring
+	Gaussian,
+	/// Lanczos with a window of 3, the sharpest
+	#[default]
+	Lanczos3
+}
+
+impl Filter {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Filter::Nearest => "nearest",
+			Filter::Triangle => "triangle",
+			Filter::CatmullRom => "catmull-rom",
+			Filter::Gaussian => "gaussian",
+			Filter::Lanczos3 => "lanczos3"
+		}
+	}
+
+	pub fn filter_type(&self) -> FilterType {
+		match self {
+			Filter::Nearest => FilterType::Nearest,
+			Filter::Triangle => FilterType::Triangle,
+			Filter::CatmullRom => FilterType::CatmullRom,
+			Filter::Gaussian => FilterType::Gaussian,
+			Filter::Lanczos3 => FilterType::Lanczos3
+		}
+	}
+}
+
+/// How an image with a different aspect ratio is fitted into the size it's resized to
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Fit {
+	/// Largest size within it keeping the aspect ratio, so one side may come out shorter
+	#[default]
+	Contain,
+	/// Smallest size covering it keeping the aspect ratio, cutting off the overhang on both sides
+	Cover,
+	/// Exactly the size, distorting the image
+	Stretch
+}
+
+impl Fit {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Fit::Contain => "contain",
+			Fit::Cover => "cover",
+			Fit::Stretch => "stretch"
+		}
+	}
+}
+
+/// Width and height, parsed from `WIDTHxHEIGHT`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Size {
+	pub width: u32,
+	pub height: u32
+}
+
+impl FromStr for Size {
+	type Err = TransformError;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		let invalid = || TransformError::InvalidSize(String::from(text));
+		let (width, height) = text.split_once(['x', 'X']).ok_or_else(invalid)?;
+		let size = Size {
+			width: width.trim().parse().map_err(|_| invalid())?,
+			height: height.trim().parse().map_err(|_| invalid())?
+		};
+		match size.width == 0 || size.height == 0 {
+			false => Ok(size),
+			true => Err(invalid())
+		}
+	}
+}
+
+/// Rectangle of an image in pixels from its top left corner, parsed from `X,Y,WIDTH,HEIGHT`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+	pub x: u32,
+	pub y: u32,
+	pub width: u32,
+	pub height: u32
+}
+
+impl FromStr for Region {
+	type Err = TransformError;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		let invalid = || TransformError::InvalidRegion(String::from(text));
+		let values = text
+			.split(',')
+			.map(|value| value.trim().parse::<u32>().map_err(|_| invalid()))
+			.collect::<Result<Vec<_>, _>>()?
+		;
+		match values[..] {
+			[x, y, width, height] if width != 0 && height != 0 => Ok(Region { x, y, width, height }),
+			_ => Err(invalid())
+		}
+	}
+}
+
+impl std::fmt::Display for Region {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(formatter, "{} x {} at {}, {}", self.width, self.height, self.x, self.y)
+	}
+}
+
+/// Clap value parser for `--scale`
+pub fn parse_scale(text: &str) -> Result<f32, TransformError> {
+	match text.trim().parse::<f32>() {
+		Ok(scale) if scale.is_finite() && scale > 0.0 => Ok(scale),
+		_ => Err(TransformError::InvalidScale(String::from(text)))
+	}
+}
+
+/// Changes made to an image before it's converted, cropping first, then resizing and scaling
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Transform {
+	pub crop: Option<Region>,
+	pub resize: Option<Size>,
+	pub fit: Fit,
+	pub scale: Option<f32>,
+	pub filter: Filter
+}
+
+impl Transform {
+	/// Whether images are left as they are
+	pub fn is_identity(&self) -> bool {
+		self.crop.is_none() && self.resize.is_none() && self.scale.is_none()
+	}
+
+	/// Size an image of `dimensions` ends up with, checking that the crop region fits in it
+	pub fn dimensions(&self, dimensions: (u32, u32)) -> Result<(u32, u32), TransformError> {
+		let (mut width, mut height) = dimensions;
+		if let Some(region) = self.crop {
+			let fits = region.x.checked_add(region.width).is_some_and(|right| right <= width)
+				&& region.y.checked_add(region.height).is_some_and(|bottom| bottom <= height);
+			if !fits {
+				return Err(TransformError::CropOutside { region, width, height });
+			}
+			(width, height) = (region.width, region.height);
+		}
+		if let Some(size) = self.resize {
+			(width, height) = match self.fit {
+				Fit::Contain => scaled((width, height), f64::min(
+					size.width as f64 / width as f64,
+					size.height as f64 / height as f64
+				)),
+				Fit::Cover | Fit::Stretch => (size.width, size.height)
+			};
+			// Rounding can't push a side past the box
+			(width, height) = (width.min(size.width), height.min(size.height));
+		}
+		if let Some(scale) = self.scale {
+			(width, height) = scaled((width, height), scale as f64);
+		}
+		Ok((width, height))
+	}
+
+	pub fn apply(&self, image: DynamicImage) -> Result<DynamicImage, TransformError> {
+		if self.is_identity() {
+			return Ok(image);
+		}
+		self.dimensions(image.dimensions())?;
+
+		let mut image = match self.crop {
+			Some(region) => image.crop_imm(region.x, region.y, region.width, region.height),
+			None => image
+		};
+		let filter = self.filter.filter_type();
+		if let Some(size) = self.resize {
+			image = match self.fit {
+				Fit::Contain => {
+					let (width, height) = Transform { resize: Some(size), fit: Fit::Contain, ..Transform::default() }
+						.dimensions(image.dimensions())?
+					;
+					image.resize_exact(width, height, filter)
+				},
+				// Scaled until both sides cover the size, then cut down to it around the center
+				Fit::Cover => {
+					let scale = f64::max(
+						size.width as f64 / image.width() as f64,
+						size.height as f64 / image.height() as f64
+					);
+					let (width, height) = scaled(image.dimensions(), scale);
+					let (width, height) = (width.max(size.width), height.max(size.height));
+					image
+						.resize_exact(width, height, filter)
+						.crop_imm((width - size.width) / 2, (height - size.height) / 2, size.width, size.height)
+				},
+				Fit::Stretch => image.resize_exact(size.width, size.height, filter)
+			};
+		}
+		if let Some(scale) = self.scale {
+			let (width, height) = scaled(image.dimensions(), scale as f64);
+			image = image.resize_exact(width, height, filter);
+		}
+		Ok(image)
+	}
+
+	/// Applies the transform to each of `frames` equally tall images stacked top to bottom, as
+	/// from `Animation::decode`, and stacks the results again
+	pub fn apply_frames(&self, image: DynamicImage, frames: usize) -> Result<DynamicImage, TransformError> {
+		if frames <= 1 || self.is_identity() {
+			return self.apply(image);
+		}
+		let (width, frame_height) = (image.width(), image.height() / frames as u32);
+		let (new_width, new_height) = self.dimensions((width, frame_height))?;
+
+		let mut stacked = DynamicImage::new(new_width, new_height * frames as u32, image.color());
+		for frame in 0..(frames as u32) {
+			let transformed = self.apply(image.crop_imm(0, frame * frame_height, width, frame_height))?;
+			// Every frame has the size checked above, so copying can't go out of bounds
+			stacked.copy_from(&transformed, 0, frame * new_height).unwrap();
+		}
+		Ok(stacked)
+	}
+
+	/// Short description such as `cropped to 64 x 64 at 0, 0, scaled by 0.5 with the lanczos3 filter`,
+	/// `None` for the identity
+	pub fn describe(&self) -> Option<String> {
+		let mut steps = Vec::new();
+		if let Some(region) = self.crop {
+			steps.push(format!("cropped to {}", region));
+		}
+		if let Some(size) = self.resize {
+			steps.push(format!("resized to {} x {} ({})", size.width, size.height, self.fit.name()));
+		}
+		if let Some(scale) = self.scale {
+			steps.push(format!("scaled by {}", scale));
+		}
+		let mut description = steps.join(", ");
+		if self.resize.is_some() || self.scale.is_some() {
+			description += &format!(" with the {} filter", self.filter.name())[..];
+		}
+		match description.is_empty() {
+			false => Some(description),
+			true => None
+		}
+	}
+}
+
+/// `dimensions` multiplied by `scale`, rounded and kept at 1 pixel or more
+fn scaled(dimensions: (u32, u32), scale: f64) -> (u32, u32) {
+	(
+		((dimensions.0 as f64 * scale).round() as u32).max(1),
+		((dimensions.1 as f64 * scale).round() as u32).max(1)
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use image::{DynamicImage, GenericImageView};
+	use super::{Filter, Fit, Region, Size, Transform, TransformError};
+
+	/// Checks that `transform` gives images of the size `dimensions` predicts, returning it
+	fn predicted(transform: Transform, width: u32, height: u32) -> (u32, u32) {
+		let image = DynamicImage::new_rgba8(width, height);
+		let dimensions = transform.dimensions((width, height)).unwrap();
+		assert_eq!(transform.apply(image).unwrap().dimensions(), dimensions, "{:?} of {} x {}", transform, width, height);
+		dimensions
+	}
+
+	fn resize(width: u32, height: u32, fit: Fit) -> Transform {
+		Transform { resize: Some(Size { width, height }), fit, filter: Filter::Nearest, ..Transform::default() }
+	}
+
+	#[test]
+	fn fit_modes() {
+		assert_eq!(predicted(resize(64, 64, Fit::Contain), 200, 100), (64, 32));
+		assert_eq!(predicted(resize(64, 64, Fit::Cover), 200, 100), (64, 64));
+		assert_eq!(predicted(resize(64, 64, Fit::Stretch), 200, 100), (64, 64));
+		// Rounding keeps at least a pixel and never passes the box
+		assert_eq!(predicted(resize(10, 10, Fit::Contain), 1000, 1), (10, 1));
+		assert_eq!(predicted(resize(7, 10, Fit::Contain), 3, 7), (4, 10));
+
+		for fit in [Fit::Contain, Fit::Cover, Fit::Stretch] {
+			for (width, height) in [(37, 23), (23, 37), (1, 100), (100, 1), (5, 5)] {
+				for (box_width, box_height) in [(16, 16), (10, 7), (7, 10), (1, 1), (64, 3)] {
+					let dimensions = predicted(resize(box_width, box_height, fit), width, height);
+					assert!(dimensions.0 <= box_width && dimensions.1 <= box_height);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn scale() {
+		let scale = |scale| Transform { scale: Some(scale), filter: Filter::Nearest, ..Transform::default() };
+		assert_eq!(predicted(scale(0.5), 37, 23), (19, 12));
+		assert_eq!(predicted(scale(1.7), 37, 23), (63, 39));
+		assert_eq!(predicted(scale(0.01), 37, 23), (1, 1));
+
+		let transform = Transform { scale: Some(0.33), ..resize(16, 16, Fit::Contain) };
+		assert_eq!(predicted(transform, 37, 23), (5, 3));
+		let transform = Transform { crop: Some(Region { x: 3, y: 1, width: 20, height: 10 }), ..transform };
+		assert_eq!(predicted(transform, 37, 23), (5, 3));
+	}
+
+	#[test]
+	fn crop() {
+		let crop = |x, y, width, height| Transform { crop: Some(Region { x, y, width, height }), ..Transform::default() };
+		assert_eq!(predicted(crop(0, 0, 37, 23), 37, 23), (37, 23));
+		assert_eq!(predicted(crop(30, 20, 7, 3), 37, 23), (7, 3));
+
+		for transform in [crop(30, 20, 8, 3), crop(30, 20, 7, 4), crop(37, 0, 1, 1), crop(u32::MAX, 0, 1, 1)] {
+			assert!(matches!(transform.dimensions((37, 23)), Err(TransformError::CropOutside { .. })));
+			assert!(matches!(transform.apply(DynamicImage::new_rgba8(37, 23)), Err(TransformError::CropOutside { .. })));
+		}
+	}
+
+	#[test]
+	fn frames() {
+		let transform = Transform { crop: Some(Region { x: 1, y: 1, width: 8, height: 6 }), ..resize(5, 5, Fit::Cover) };
+		let image = transform.apply_frames(DynamicImage::new_rgba8(10, 24), 3).unwrap();
+		assert_eq!(image.dimensions(), (5, 15));
+		assert!(matches!(transform.apply_frames(DynamicImage::new_rgba8(10, 15), 3), Err(TransformError::CropOutside { .. })));
+	}
+}
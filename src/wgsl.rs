@@ -0,0 +1,171 @@
+use std::io::{self, Write};
+use crate::{
+	animation,
+	atlas,
+	encoding::{Encoder, Position},
+	hash,
+	number::NumberFormatter,
+	pixels::Pixels,
+	skybox,
+	ConvertOptions,
+	Layout,
+	PixelOrder,
+	WrapMode
+};
+
+/// One `vec4<f32>` per pixel in a module-scope `const`, or in a `var<private>` when the
+/// sampling, frame or skybox helpers need to index it with runtime values, which WGSL doesn't allow on constants
+pub(crate) struct Vec4F32Encoder<'a> {
+	options: &'a ConvertOptions,
+	pixels: &'a Pixels,
+	order: PixelOrder,
+	nested: bool,
+	formatter: NumberFormatter
+}
+
+impl<'a> Vec4F32Encoder<'a> {
+	pub fn new(options: &'a ConvertOptions, pixels: &'a Pixels, order: PixelOrder) -> Self {
+		Vec4F32Encoder {
+			options,
+			pixels,
+			order,
+			nested: order.layout != Layout::Flat,
+			formatter: NumberFormatter::default()
+		}
+	}
+
+	fn runtime_indexed(&self) -> bool {
+		self.options.helpers || !self.options.frame_durations.is_empty() || self.options.skybox.is_some()
+	}
+
+	fn array_type(&self) -> String {
+		let (outer_len, inner_len) = (self.order.outer_len(), self.order.inner_len());
+		match self.nested {
+			true => format!("array<array<vec4<f32>, {}>, {}>", inner_len, outer_len),
+			false => format!("array<vec4<f32>, {}>", (outer_len as u64) * (inner_len as u64))
+		}
+	}
+}
+
+impl Encoder for Vec4F32Encoder<'_> {
+	fn access(&self) -> String {
+		self.order.element(&self.options.name, self.nested, "x", "y")
+	}
+
+	fn format_description(&self) -> String {
+		format!(
+			"{} {}, {}",
+			match self.runtime_indexed() {
+				false => "const",
+				true => "var<private>"
+			},
+			self.array_type(),
+			self.pixels.value_format()
+		)
+	}
+
+	fn requirement(&self) -> (&'static str, String) {
+		("Minimum WebGPU version", String::from("1.0"))
+	}
+
+	fn header(&mut self, output: &mut dyn Write) -> io::Result<()> {
+		write!(output, "{}", self.order.header(&self.access()))?;
+		hash::write_source_hash(output, self.options)?;
+		writeln!(output)?;
+		atlas::write_sprites(output, self.options, self.order)?;
+		animation::write_frame_table(output, self.options)?;
+		match self.runtime_indexed() {
+			false => writeln!(output, "const {} = {}(", self.options.name, self.array_type()),
+			true => writeln!(
+				output,
+				"var<private> {0}: {1} = {1}(",
+				self.options.name,
+				self.array_type()
+			)
+		}
+	}
+
+	fn row_begin(&mut self, output: &mut dyn Write, _row: u32) -> io::Result<()> {
+		output.write_all(b"\t")?;
+		if self.nested {
+			write!(output, "array<vec4<f32>, {}>(", self.order.inner_len())?;
+		}
+		Ok(())
+	}
+
+	fn pixel(&mut self, output: &mut dyn Write, position: Position) -> io::Result<()> {
+		output.write_all(self.formatter.vec4_f32(self.pixels.get(position.x, position.y)).as_bytes())?;
+		if (position.inner + 1) != self.order.inner_len() {
+			output.write_all(b", ")?;
+		} else if self.nested {
+			output.write_all(b")")?;
+		}
+		Ok(())
+	}
+
+	fn row_end(&mut self, output: &mut dyn Write, row: u32) -> io::Result<()> {
+		output.write_all(match (row + 1) == self.order.outer_len() {
+			false => b",\n",
+			true => b"\n"
+		})
+	}
+
+	fn footer(&mut self, output: &mut dyn Write) -> io::Result<()> {
+		writeln!(output, ");")?;
+		let texel = self.order.element(&self.options.name, self.nested, "wrapped.x", "wrapped.y");
+		if self.options.helpers {
+			output.write_all(helpers(&self.options.name, self.order.dimensions, &texel, self.options.wrap).as_bytes())?;
+		}
+		animation::write_frame_helpers(output, self.options, self.order, &texel)?;
+		skybox::write_sample_function(output, self.options, self.order, &texel)?;
+		Ok(())
+	}
+}
+
+/// WGSL counterpart of `sampling::helpers`, reading the in-range texel at `wrapped` through `texel`
+fn helpers(name: &str, dimensions: (u32, u32), texel: &str, wrap: WrapMode) -> String {
+	let mut output = String::new();
+
+	output += &format!("\nconst {}_size = vec2<i32>({}, {});\n", name, dimensions.0, dimensions.1)[..];
+
+	// `i32` remainders take the sign of the coordinate, leaving negative ones out of range without floor division
+	output += &format!("\nfn {}_wrap(coord: vec2<i32>) -> vec2<i32> {{\n", name)[..];
+	output += &match wrap {
+		WrapMode::Clamp => format!("\treturn clamp(coord, vec2<i32>(0), {}_size - 1);\n", name),
+		WrapMode::Repeat => format!(
+			"\treturn coord - {0}_size * vec2<i32>(floor(vec2<f32>(coord) / vec2<f32>({0}_size)));\n",
+			name
+		),
+		WrapMode::Mirror => format!(
+			concat!(
+				"\tlet period = {}_size * 2;\n",
+				"\tlet wrapped = coord - period * vec2<i32>(floor(vec2<f32>(coord) / vec2<f32>(period)));\n",
+				"\treturn min(wrapped, period - 1 - wrapped);\n"
+			),
+			name
+		)
+	}[..];
+	output += "}\n";
+
+	output += &format!("\nfn {}_fetch(coord: vec2<i32>) -> vec4<f32> {{\n", name)[..];
+	output += &format!("\tlet wrapped = {}_wrap(coord);\n", name)[..];
+	output += &format!("\treturn {};\n", texel)[..];
+	output += "}\n";
+
+	output += &format!("\nfn {}_sample_nearest(uv: vec2<f32>) -> vec4<f32> {{\n", name)[..];
+	output += &format!("\treturn {0}_fetch(vec2<i32>(floor(uv * vec2<f32>({0}_size))));\n", name)[..];
+	output += "}\n";
+
+	output += &format!("\nfn {}_sample_bilinear(uv: vec2<f32>) -> vec4<f32> {{\n", name)[..];
+	output += &format!("\tlet position = uv * vec2<f32>({}_size) - 0.5;\n", name)[..];
+	output += "\tlet base = vec2<i32>(floor(position));\n";
+	output += "\tlet weight = fract(position);\n";
+	output += "\treturn mix(\n";
+	output += &format!("\t\tmix({0}_fetch(base), {0}_fetch(base + vec2<i32>(1, 0)), weight.x),\n", name)[..];
+	output += &format!("\t\tmix({0}_fetch(base + vec2<i32>(0, 1)), {0}_fetch(base + vec2<i32>(1, 1)), weight.x),\n", name)[..];
+	output += "\t\tweight.y\n";
+	output += "\t);\n";
+	output += "}\n";
+
+	output
+}